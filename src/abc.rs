@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::error::Error;
+use crate::interval_sequences::SequenceNote;
+use crate::intervals::Interval;
+use crate::score::{Annotation, annotations, is_treble, measures, part_events};
+
+// measures written on one line of music
+const MEASURES_PER_LINE: usize = 4;
+
+// pitch of the note, C is middle C. Accidentals hold until the end of the measure,
+// so they are written whenever the note differs from the previous one of its letter and octave.
+fn pitch(note: &SequenceNote, accidentals: &mut HashMap<(i32, i32), i32>) -> String {
+    let note = &note.note;
+    let accidental = note.get_accidental();
+    let octave = note.get_octave();
+    let previous = accidentals.insert((note.get_letter().get_index(), octave), accidental).unwrap_or(0);
+    let sign = match accidental {
+        _ if accidental == previous => String::new(),
+        0 => String::from("="),
+        _ => if accidental > 0 {"^"} else {"_"}.repeat(accidental.unsigned_abs() as usize)
+    };
+    let letter = note.get_letter().to_str();
+    if octave >= 5 {
+        format!("{}{}{}", sign, letter.to_lowercase(), "'".repeat((octave - 5) as usize))
+    } else {
+        format!("{}{}{}", sign, letter, ",".repeat((4 - octave).max(0) as usize))
+    }
+}
+
+// syllable of a lyrics line, without the spaces and hyphens separating syllables
+fn syllable(text: &str) -> String {
+    text.replace('-', "\\-").replace(' ', "~")
+}
+
+// Tune of the sequence with one voice per instrument in quarter notes, the annotations as lyrics.
+// Tied continuations hold the syllable of their note, notes without annotation skip it.
+pub fn to_abc(notes: &[SequenceNote], intervals: &[Interval], split: bool, annotation: Annotation) -> String {
+    let texts = annotations(intervals, notes, annotation);
+    let parts = part_events(notes.len(), split);
+    let mut abc = String::from("X:1\nT:Just out of tune\nM:4/4\nL:1/4\n");
+    for (ipart, events) in parts.iter().enumerate() {
+        abc.push_str(&format!("V:{} clef={} name=\"Instrument {}\"\n", ipart+1,
+                              if is_treble(events, notes) {"treble"} else {"bass"}, ipart+1));
+    }
+    abc.push_str("K:C\n");
+    for (ipart, events) in parts.iter().enumerate() {
+        abc.push_str(&format!("V:{}\n", ipart+1));
+        let measures = measures(events);
+        for (iline, line) in measures.chunks(MEASURES_PER_LINE).enumerate() {
+            let mut music = Vec::new();
+            let mut lyrics = Vec::new();
+            for pieces in line {
+                let mut accidentals = HashMap::new();
+                let notation: Vec<String> = pieces.iter()
+                    .map(|piece| {
+                        let length = if piece.beats == 1 {String::new()} else {piece.beats.to_string()};
+                        match piece.note {
+                            Some(inote) => {
+                                lyrics.push(match (&texts[inote], piece.tied_from) {
+                                    (_, true) => String::from("_"),
+                                    (Some(text), false) => syllable(text),
+                                    (None, false) => String::from("*")
+                                });
+                                format!("{}{}{}", pitch(&notes[inote], &mut accidentals), length,
+                                        if piece.tied_to {"-"} else {""})
+                            }
+                            None => format!("z{}", length)
+                        }
+                    })
+                    .collect();
+                music.push(notation.join(" "));
+            }
+            let last = (iline + 1)*MEASURES_PER_LINE >= measures.len();
+            abc.push_str(&format!("{} {}\n", music.join(" | "), if last {"|]"} else {"|"}));
+            if !lyrics.is_empty() {
+                abc.push_str(&format!("w: {}\n", lyrics.join(" ")));
+            }
+        }
+    }
+    abc
+}
+
+pub fn write_abc(path: &Path, notes: &[SequenceNote], intervals: &[Interval], split: bool,
+                 annotation: Annotation) -> Result<(), Error> {
+    fs::write(path, to_abc(notes, intervals, split, annotation))
+        .map_err(|err| Error::Export(format!("Cannot write ABC file {}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interval_sequences::test_notes;
+    use crate::notes::Note;
+    use crate::score::test_score;
+
+    #[test]
+    fn pitches() {
+        let notes = test_notes(&[], Note::new("Ebb", 6));
+        assert_eq!(pitch(&notes[0], &mut HashMap::new()), "__e'");
+        // accidentals hold for the letter and octave until the end of the measure
+        let mut accidentals = HashMap::new();
+        let notes: Vec<SequenceNote> = ["D#", "D#", "D", "D#"].iter()
+            .map(|name| test_notes(&[], Note::new(name, 2)).remove(0))
+            .collect();
+        assert_eq!(notes.iter().map(|note| pitch(note, &mut accidentals)).collect::<Vec<_>>(),
+                   vec!["^D,,", "D,,", "=D,,", "^D,,"]);
+        assert_eq!(syllable("-11.7"), "\\-11.7");
+        assert_eq!(syllable("major third"), "major~third");
+    }
+
+    #[test]
+    fn single_voice() {
+        let (notes, intervals) = test_score(Note::new("C", 4));
+        assert_eq!(to_abc(&notes, &intervals, false, Annotation::Cents), "\
+X:1
+T:Just out of tune
+M:4/4
+L:1/4
+V:1 clef=treble name=\"Instrument 1\"
+K:C
+V:1
+C D B, ^D | ^C z3 |]
+w: +0.0 +3.9 \\-11.7 \\-25.4 \\-7.8
+");
+    }
+
+    #[test]
+    fn split_voices() {
+        let (notes, intervals) = test_score(Note::new("C", 3));
+        assert_eq!(to_abc(&notes, &intervals, true, Annotation::Intervals), "\
+X:1
+T:Just out of tune
+M:4/4
+L:1/4
+V:1 clef=bass name=\"Instrument 1\"
+V:2 clef=bass name=\"Instrument 2\"
+K:C
+V:1
+C,2 B,,2 | ^C, z3 |]
+w: * IMinorThird IMinorWholeTone
+V:2
+z D,2 ^D,- | ^D, z3 |]
+w: MajorWholeTone MajorThird _
+");
+    }
+}
@@ -0,0 +1,279 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use crate::error::Error;
+use crate::midi::MidiNote;
+
+// number of partials of the additive timbre
+const ADDITIVE_PARTIALS: u32 = 8;
+// level of the rendering relative to full scale, leaving headroom for rounding
+const PEAK_LEVEL: f64 = 0.9;
+// level of the drone relative to the notes
+const DRONE_LEVEL: f64 = 0.5;
+// highest sample rate in Hz that is rendered
+pub const MAX_SAMPLE_RATE: u32 = 384_000;
+// bytes of the WAV header counted in its RIFF size, the 16 bit samples follow
+const WAV_HEADER_LEN: u32 = 36;
+
+// Waveform the notes are synthesized with
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Timbre {
+    Sine,
+    // band limited sawtooth with every partial below the Nyquist frequency
+    Sawtooth,
+    // organ like sum of partials falling off with the square of their number
+    Additive
+}
+
+impl Timbre {
+    // amplitude of the partial of the given number
+    fn partial(&self, number: u32) -> f64 {
+        match self {
+            Timbre::Sine => if number == 1 {1.0} else {0.0},
+            Timbre::Sawtooth => 1.0/number as f64,
+            Timbre::Additive => if number <= ADDITIVE_PARTIALS {1.0/(number*number) as f64} else {0.0}
+        }
+    }
+}
+
+impl FromStr for Timbre {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "sine" => Ok(Timbre::Sine),
+            "sawtooth" | "saw" => Ok(Timbre::Sawtooth),
+            "additive" => Ok(Timbre::Additive),
+            _ => Err(Error::InvalidOption(format!("'{}' is not a timbre, use sine, sawtooth or additive", s)))
+        }
+    }
+}
+
+impl fmt::Display for Timbre {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timbre::Sine => write!(f, "sine"),
+            Timbre::Sawtooth => write!(f, "sawtooth"),
+            Timbre::Additive => write!(f, "additive")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RenderOptions {
+    pub timbre: Timbre,
+    pub sample_rate: u32,
+    // length of a note in seconds
+    pub note_duration: f64,
+    // linear fade in and fade out of every note in seconds
+    pub attack: f64,
+    pub release: f64,
+    // frequency held below the whole sequence
+    pub drone: Option<f64>
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {timbre: Timbre::Sine, sample_rate: 44100, note_duration: 1.0, attack: 0.02, release: 0.1, drone: None}
+    }
+}
+
+// tone of the timbre at the frequency, fading in and out within the duration
+fn add_tone(samples: &mut [f64], freq: f64, start: f64, duration: f64, level: f64, options: &RenderOptions) {
+    let rate = options.sample_rate as f64;
+    let nyquist = rate/2.0;
+    let partials: Vec<(f64, f64)> = (1..)
+        .map(|number| (number as f64*freq, options.timbre.partial(number)))
+        .take_while(|(partial_freq, _)| *partial_freq < nyquist)
+        .filter(|(_, amplitude)| *amplitude != 0.0)
+        .collect();
+    // fades that do not fit into the note are shortened in proportion
+    let fades = (options.attack + options.release).max(duration);
+    let attack = options.attack*duration/fades;
+    let release = options.release*duration/fades;
+    let first = (start*rate).round() as usize;
+    let last = (((start + duration)*rate).round() as usize).min(samples.len());
+    for (isample, sample) in samples.iter_mut().enumerate().take(last).skip(first) {
+        let time = (isample - first) as f64/rate;
+        let envelope = if time < attack {
+            time/attack
+        } else if time > duration - release {
+            (duration - time)/release
+        } else {
+            1.0
+        };
+        let wave: f64 = partials.iter()
+            .map(|(partial_freq, amplitude)| amplitude*(2.0*PI*partial_freq*time).sin())
+            .sum();
+        *sample += level*envelope.clamp(0.0, 1.0)*wave;
+    }
+}
+
+// Samples of all tracks played together, with the drone over the whole length,
+// normalized to the peak level
+pub fn render(tracks: &[Vec<MidiNote>], options: &RenderOptions) -> Result<Vec<f64>, Error> {
+    if !(1..=MAX_SAMPLE_RATE).contains(&options.sample_rate) {
+        return Err(Error::InvalidOption(format!("The sample rate must lie between 1 and {} Hz", MAX_SAMPLE_RATE)));
+    }
+    if !(options.note_duration > 0.0 && options.attack >= 0.0 && options.release >= 0.0) {
+        return Err(Error::InvalidOption(String::from("The note duration must be positive, attack and release not negative")));
+    }
+    let length = tracks.iter().flatten()
+        .map(|note| note.start + note.duration)
+        .fold(0.0, f64::max);
+    // checked before allocating, the samples have to fit into a WAV file
+    let nsamples = (length*options.sample_rate as f64).round();
+    if nsamples > ((u32::MAX - WAV_HEADER_LEN)/2) as f64 {
+        return Err(Error::Export(format!("The rendering of {:.1} seconds is too long for a WAV file", length)));
+    }
+    let mut samples = vec![0.0; nsamples as usize];
+    for note in tracks.iter().flatten() {
+        add_tone(&mut samples, note.freq, note.start, note.duration, 1.0, options);
+    }
+    if let Some(drone) = options.drone {
+        add_tone(&mut samples, drone, 0.0, length, DRONE_LEVEL, options);
+    }
+    let peak = samples.iter().fold(0.0, |peak: f64, sample| peak.max(sample.abs()));
+    if peak > 0.0 {
+        samples.iter_mut().for_each(|sample| *sample *= PEAK_LEVEL/peak);
+    }
+    Ok(samples)
+}
+
+// mono WAV file with 16 bit samples
+pub fn to_wav(samples: &[f64], sample_rate: u32) -> Result<Vec<u8>, Error> {
+    let too_long = || Error::Export(format!("{} samples are too many for a WAV file", samples.len()));
+    let data_len = samples.len().checked_mul(2).and_then(|len| u32::try_from(len).ok()).ok_or_else(too_long)?;
+    let riff_len = data_len.checked_add(WAV_HEADER_LEN).ok_or_else(too_long)?;
+    let byte_rate = sample_rate.checked_mul(2)
+        .ok_or_else(|| Error::Export(format!("The sample rate of {} Hz is too high for a WAV file", sample_rate)))?;
+    let mut bytes = b"RIFF".to_vec();
+    bytes.extend(riff_len.to_le_bytes());
+    bytes.extend(b"WAVEfmt ");
+    bytes.extend(16u32.to_le_bytes());
+    // PCM, one channel
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(sample_rate.to_le_bytes());
+    bytes.extend(byte_rate.to_le_bytes());
+    bytes.extend(2u16.to_le_bytes());
+    bytes.extend(16u16.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend(data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend(((sample.clamp(-1.0, 1.0)*i16::MAX as f64).round() as i16).to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+pub fn write_wav(path: &Path, tracks: &[Vec<MidiNote>], options: &RenderOptions) -> Result<(), Error> {
+    let samples = render(tracks, options)?;
+    fs::write(path, to_wav(&samples, options.sample_rate)?)
+        .map_err(|err| Error::Export(format!("Cannot write WAV file {}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RATE: u32 = 8000;
+
+    fn options(timbre: Timbre) -> RenderOptions {
+        RenderOptions {timbre, sample_rate: RATE, ..RenderOptions::default()}
+    }
+
+    fn note(freq: f64, start: f64, duration: f64) -> MidiNote {
+        MidiNote {freq, start, duration}
+    }
+
+    // amplitude of the frequency in the samples (Goertzel algorithm)
+    fn amplitude(samples: &[f64], freq: f64) -> f64 {
+        let coefficient = 2.0*(2.0*PI*freq/RATE as f64).cos();
+        let (mut previous, mut before) = (0.0, 0.0);
+        for sample in samples {
+            let current = sample + coefficient*previous - before;
+            before = previous;
+            previous = current;
+        }
+        (previous*previous + before*before - coefficient*previous*before).sqrt()*2.0/samples.len() as f64
+    }
+
+    // frequency from the rising zero crossings
+    fn zero_crossing_freq(samples: &[f64]) -> f64 {
+        let crossings: Vec<f64> = samples.windows(2).enumerate()
+            .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+            .map(|(isample, pair)| isample as f64 + pair[0]/(pair[0] - pair[1]))
+            .collect();
+        (crossings.len() - 1) as f64*RATE as f64/(crossings[crossings.len()-1] - crossings[0])
+    }
+
+    #[test]
+    fn timbres() {
+        assert_eq!("Saw".parse::<Timbre>(), Ok(Timbre::Sawtooth));
+        for timbre in [Timbre::Sine, Timbre::Sawtooth, Timbre::Additive] {
+            assert_eq!(timbre.to_string().parse::<Timbre>(), Ok(timbre));
+        }
+        assert!(matches!("square".parse::<Timbre>(), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn just_frequency() {
+        // a syntonic comma above A4 lies about 5.5 Hz above the tempered A4
+        let samples = render(&[vec![note(445.5, 0.0, 2.0)]], &options(Timbre::Sine)).unwrap();
+        assert_eq!(samples.len(), 2*RATE as usize);
+        let sustain = &samples[RATE as usize/2..3*RATE as usize/2];
+        assert!((zero_crossing_freq(sustain) - 445.5).abs() < 0.05);
+        assert!((amplitude(sustain, 445.5) - PEAK_LEVEL).abs() < 0.01);
+        assert!(amplitude(sustain, 440.0) < 0.1*amplitude(sustain, 445.5));
+    }
+
+    #[test]
+    fn envelope() {
+        let samples = render(&[vec![note(440.0, 0.0, 1.0), note(660.0, 1.0, 1.0)]], &options(Timbre::Additive)).unwrap();
+        assert_eq!(samples[0], 0.0);
+        assert!(samples.iter().all(|sample| sample.abs() <= PEAK_LEVEL + 1e-12));
+        assert!((samples.iter().fold(0.0, |peak: f64, sample| peak.max(sample.abs())) - PEAK_LEVEL).abs() < 1e-12);
+        // the notes fade out before the next one starts
+        let second = RATE as usize;
+        assert!(samples[second-4..second+4].iter().all(|sample| sample.abs() < 0.02));
+        let first_note = &samples[second/4..3*second/4];
+        assert!(amplitude(first_note, 440.0) > 10.0*amplitude(first_note, 660.0));
+        // partials of the additive timbre
+        assert!((amplitude(first_note, 880.0)/amplitude(first_note, 440.0) - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn band_limited() {
+        let samples = render(&[vec![note(1500.0, 0.0, 1.0)]], &options(Timbre::Sawtooth)).unwrap();
+        let sustain = &samples[RATE as usize/4..3*RATE as usize/4];
+        assert!((amplitude(sustain, 3000.0)/amplitude(sustain, 1500.0) - 0.5).abs() < 0.01);
+        // the third partial lies above the Nyquist frequency and is left out instead of aliasing to 3500 Hz
+        assert!(amplitude(sustain, 3500.0) < 1e-3);
+    }
+
+    #[test]
+    fn drone() {
+        let options = RenderOptions {drone: Some(440.0), ..options(Timbre::Sine)};
+        let tracks = vec![vec![note(445.5, 0.0, 1.0)], vec![note(594.0, 1.0, 1.0)]];
+        let samples = render(&tracks, &options).unwrap();
+        let second = &samples[5*RATE as usize/4..7*RATE as usize/4];
+        assert!((amplitude(second, 440.0)/amplitude(second, 594.0) - DRONE_LEVEL).abs() < 0.01);
+        assert!(matches!(render(&tracks, &RenderOptions {sample_rate: 0, ..options}), Err(Error::InvalidOption(_))));
+        assert!(matches!(render(&tracks, &RenderOptions {sample_rate: MAX_SAMPLE_RATE + 1, ..options}), Err(Error::InvalidOption(_))));
+        // too long for a WAV file, rejected without allocating the samples
+        assert!(matches!(render(&[vec![note(440.0, 0.0, 1e9)]], &options), Err(Error::Export(_))));
+    }
+
+    #[test]
+    fn wav() {
+        let bytes = to_wav(&[0.0, 1.0, -1.0, 0.5], 8000).unwrap();
+        assert_eq!(&bytes[..12], b"RIFF\x2c\x00\x00\x00WAVE");
+        assert_eq!(&bytes[12..36], b"fmt \x10\x00\x00\x00\x01\x00\x01\x00\x40\x1f\x00\x00\x80\x3e\x00\x00\x02\x00\x10\x00");
+        assert_eq!(&bytes[36..44], b"data\x08\x00\x00\x00");
+        let samples: Vec<i16> = bytes[44..].chunks(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect();
+        assert_eq!(samples, vec![0, 32767, -32767, 16384]);
+        assert_eq!(to_wav(&[0.0], 1 << 31), Err(Error::Export(String::from("The sample rate of 2147483648 Hz is too high for a WAV file"))));
+    }
+}
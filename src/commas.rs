@@ -0,0 +1,348 @@
+use rug::Rational;
+use crate::intervals::Interval;
+use crate::monzo::{Monzo, nth_prime};
+use crate::interval_set::IntervalSet;
+
+// well known commas, the ratios are larger than one
+const COMMA_NAMES: [(u32, u32, &str); 37] = [
+    (81, 80, "syntonic comma"),
+    (128, 125, "lesser diesis"),
+    (648, 625, "greater diesis"),
+    (2048, 2025, "diaschisma"),
+    (32805, 32768, "schisma"),
+    (531441, 524288, "Pythagorean comma"),
+    (3125, 3072, "magic comma"),
+    (250, 243, "maximal diesis"),
+    (15625, 15552, "kleisma"),
+    (20000, 19683, "minimal diesis"),
+    (393216, 390625, "Würschmidt comma"),
+    (25, 24, "chromatic semitone"),
+    (135, 128, "major chroma"),
+    (64, 63, "septimal comma"),
+    (225, 224, "septimal kleisma"),
+    (36, 35, "septimal diesis"),
+    (50, 49, "jubilisma"),
+    (49, 48, "slendro diesis"),
+    (126, 125, "septimal semicomma"),
+    (245, 243, "sensamagic comma"),
+    (28, 27, "septimal third tone"),
+    (21, 20, "septimal chromatic semitone"),
+    (4375, 4374, "ragisma"),
+    (2401, 2400, "breedsma"),
+    (1029, 1024, "gamelisma"),
+    (33, 32, "undecimal comma"),
+    (243, 242, "rastma"),
+    (99, 98, "mothwellsma"),
+    (121, 120, "biyatisma"),
+    (100, 99, "ptolemisma"),
+    (385, 384, "undecimal kleisma"),
+    (45, 44, "undecimal fifth tone"),
+    (55, 54, "telepathma"),
+    (65, 64, "thirteenth-partial chroma"),
+    (40, 39, "tridecimal minor diesis"),
+    (144, 143, "grossma"),
+    (676, 675, "island comma"),
+];
+
+// name of a well known comma, independent of its direction
+pub fn comma_name(freq_scale: &Rational) -> Option<&'static str> {
+    let freq_scale = if *freq_scale < 1 {freq_scale.clone().recip()} else {freq_scale.clone()};
+    COMMA_NAMES.iter()
+        .find(|(numer, denom, _)| freq_scale == Rational::from((*numer, *denom)))
+        .map(|(_, _, name)| *name)
+}
+
+// Combination of intervals without half steps that changes the frequency
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comma {
+    interval_set: IntervalSet,
+    name: Option<&'static str>
+}
+
+impl Comma {
+    pub fn new(interval_set: IntervalSet) -> Self {
+        assert_eq!(interval_set.get_half_steps(), 0, "A comma must not change the number of half steps");
+        let name = comma_name(&interval_set.get_freq_scale());
+        Self {interval_set, name}
+    }
+
+    pub fn get_name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    pub fn get_interval_set(&self) -> &IntervalSet {
+        &self.interval_set
+    }
+
+    pub fn get_freq_scale(&self) -> Rational {
+        self.interval_set.get_freq_scale()
+    }
+
+    pub fn get_monzo(&self) -> Monzo {
+        self.interval_set.get_monzo()
+    }
+
+    pub fn cents(&self) -> f64 {
+        self.get_monzo().cents()
+    }
+}
+
+// size in cents that weighs as much as a unit exponent of the prime 2 in the comma reduction
+const CENTS_WEIGHT: f64 = 10.0;
+
+// Lattice vector with its half steps and monzo exponents as values,
+// and the interval counts it is made of
+#[derive(Debug, Clone)]
+struct Row {
+    values: Vec<i64>,
+    counts: Vec<i64>
+}
+
+impl Row {
+    fn sub_multiple(&mut self, factor: i64, other: &Row) {
+        for (value, other_value) in self.values.iter_mut().zip(&other.values) {
+            *value -= factor*other_value;
+        }
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count -= factor*other_count;
+        }
+    }
+
+    fn negate(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = -*value);
+        self.counts.iter_mut().for_each(|count| *count = -*count);
+    }
+}
+
+// Euclid's algorithm on one value column of the rows. At most one row keeps
+// a non-zero value, which is removed from the rows and returned.
+fn eliminate_column(rows: &mut Vec<Row>, icol: usize) -> Option<Row> {
+    loop {
+        let ipivot = (0..rows.len())
+            .filter(|&irow| rows[irow].values[icol] != 0)
+            .min_by_key(|&irow| rows[irow].values[icol].abs())?;
+        let pivot = rows[ipivot].clone();
+        let mut reduced = false;
+        for (irow, row) in rows.iter_mut().enumerate() {
+            if irow != ipivot && row.values[icol] != 0 {
+                row.sub_multiple(row.values[icol]/pivot.values[icol], &pivot);
+                reduced = true;
+            }
+        }
+        if !reduced {
+            return Some(rows.remove(ipivot));
+        }
+    }
+}
+
+// Gram-Schmidt coefficients mu and squared lengths of the orthogonalized vectors
+fn gram_schmidt(vectors: &[Vec<f64>]) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x*y).sum::<f64>();
+    let mut orthogonal: Vec<Vec<f64>> = Vec::new();
+    let mut mu = vec![vec![0.0; vectors.len()]; vectors.len()];
+    let mut norms = Vec::new();
+    for (i, vector) in vectors.iter().enumerate() {
+        let mut ortho = vector.clone();
+        for j in 0..i {
+            mu[i][j] = dot(vector, &orthogonal[j])/norms[j];
+            for (x, y) in ortho.iter_mut().zip(&orthogonal[j]) {
+                *x -= mu[i][j]*y;
+            }
+        }
+        norms.push(dot(&ortho, &ortho));
+        orthogonal.push(ortho);
+    }
+    (mu, norms)
+}
+
+// Lenstra-Lenstra-Lovász reduction of linearly independent rows,
+// measured by the embedding returned from coords.
+fn lll_reduce<F>(rows: &mut [Row], coords: F)
+    where F: Fn(&Row) -> Vec<f64> {
+    let embed = |rows: &[Row]| gram_schmidt(&rows.iter().map(&coords).collect::<Vec<_>>());
+    let mut k = 1;
+    while k < rows.len() {
+        for j in (0..k).rev() {
+            let (mu, _) = embed(rows);
+            let factor = mu[k][j].round() as i64;
+            if factor != 0 {
+                let row = rows[j].clone();
+                rows[k].sub_multiple(factor, &row);
+            }
+        }
+        let (mu, norms) = embed(rows);
+        if norms[k] >= (0.75 - mu[k][k-1]*mu[k][k-1])*norms[k-1] {
+            k += 1;
+        } else {
+            rows.swap(k, k-1);
+            k = (k-1).max(1);
+        }
+    }
+}
+
+// Shortens the interval counts of a row by subtracting combinations of intervals without effect.
+fn shorten_counts(row: &mut Row, unisons: &[Row]) {
+    let dot = |a: &[i64], b: &[i64]| a.iter().zip(b).map(|(x, y)| x*y).sum::<i64>();
+    loop {
+        let mut shortened = false;
+        for unison in unisons {
+            let overlap = dot(&row.counts, &unison.counts);
+            let norm = dot(&unison.counts, &unison.counts);
+            let factor = (overlap as f64/norm as f64).round() as i64;
+            // only apply strict improvements to guarantee termination
+            if factor != 0 && factor*factor*norm < 2*factor*overlap {
+                row.sub_multiple(factor, unison);
+                shortened = true;
+            }
+        }
+        if !shortened {
+            break;
+        }
+    }
+}
+
+// Reduced basis of the commas that the vocabulary can form. Every combination of
+// intervals without half steps changes the frequency by a product of these commas.
+// The commas are oriented upwards and sorted by size.
+pub fn comma_basis(vocabulary: &[Interval]) -> Vec<Comma> {
+    let nprimes = vocabulary.iter()
+        .map(|interval| interval.get_monzo().get_exponents().len())
+        .max()
+        .unwrap_or(0);
+    let mut rows: Vec<Row> = vocabulary.iter().enumerate().map(|(idim, interval)| {
+        let mut values = vec![interval.get_half_steps() as i64];
+        values.resize(nprimes+1, 0);
+        for (value, &exponent) in values[1..].iter_mut().zip(interval.get_monzo().get_exponents()) {
+            *value = exponent as i64;
+        }
+        let mut counts = vec![0; vocabulary.len()];
+        counts[idim] = 1;
+        Row {values, counts}
+    }).collect();
+    // the rows left after removing the half step pivot span the combinations without half steps,
+    // the monzo pivots among them span the commas, and the remaining rows do not change the frequency
+    eliminate_column(&mut rows, 0);
+    let mut commas: Vec<Row> = (1..=nprimes)
+        .filter_map(|icol| eliminate_column(&mut rows, icol))
+        .collect();
+    let mut unisons = rows;
+    // Tenney weighted monzos favour commas of simple ratios, the size in cents favours small commas.
+    // The size matters when the vocabulary maps a ratio to different numbers of half steps,
+    // as the ratio divided by itself then forms a comma as large as an octave.
+    lll_reduce(&mut commas, |row| {
+        let mut coords: Vec<f64> = row.values[1..].iter().enumerate()
+            .map(|(iprime, &exponent)| exponent as f64*(nth_prime(iprime) as f64).log2())
+            .collect();
+        let cents: f64 = coords.iter().sum::<f64>()*1200.0;
+        coords.push(cents/CENTS_WEIGHT);
+        coords
+    });
+    lll_reduce(&mut unisons, |row| row.counts.iter().map(|&count| count as f64).collect());
+    let mut commas: Vec<Comma> = commas.into_iter().map(|mut row| {
+        shorten_counts(&mut row, &unisons);
+        let monzo = Monzo::new(row.values[1..].iter().map(|&exponent| exponent as i32).collect());
+        if monzo < Monzo::unison() {
+            row.negate();
+        }
+        let counts = row.counts.iter()
+            .map(|&count| i32::try_from(count).expect("Comma exceeds the interval count range"))
+            .collect();
+        Comma::new(IntervalSet::new_from_vals(vocabulary, counts))
+    }).collect();
+    commas.sort_by_key(|comma| comma.get_monzo());
+    commas
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interval_vocabulary::builtin_vocabulary;
+
+    fn ratios(commas: &[Comma]) -> Vec<Rational> {
+        commas.iter().map(|comma| comma.get_freq_scale()).collect()
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(comma_name(&Rational::from((81,80))), Some("syntonic comma"));
+        assert_eq!(comma_name(&Rational::from((80,81))), Some("syntonic comma"));
+        assert_eq!(comma_name(&Rational::from((531441,524288))), Some("Pythagorean comma"));
+        assert_eq!(comma_name(&Rational::from((3,2))), None);
+        // every name describes a comma larger than one and appears once
+        for (i, (numer, denom, name)) in COMMA_NAMES.iter().enumerate() {
+            assert!(numer > denom);
+            assert!(COMMA_NAMES[i+1..].iter().all(|(n, d, other)| (n, d) != (numer, denom) && other != name));
+        }
+    }
+
+    #[test]
+    fn pythagorean() {
+        let commas = comma_basis(&builtin_vocabulary(3));
+        assert_eq!(ratios(&commas), vec![Rational::from((531441,524288))]);
+        assert_eq!(commas[0].get_name(), Some("Pythagorean comma"));
+        assert_eq!(commas[0].get_interval_set().get_half_steps(), 0);
+    }
+
+    #[test]
+    fn five_limit() {
+        let vocabulary = builtin_vocabulary(5);
+        let commas = comma_basis(&vocabulary);
+        assert_eq!(ratios(&commas), vec![Rational::from((81,80)), Rational::from((128,125))]);
+        assert_eq!(commas[0].get_name(), Some("syntonic comma"));
+        assert_eq!(commas[1].get_name(), Some("lesser diesis"));
+        // the syntonic comma is the difference of the whole tones
+        assert_eq!(commas[0].get_interval_set().num_intervals(), 2);
+        assert!((commas[0].cents() - 21.506).abs() < 1e-3);
+    }
+
+    #[test]
+    fn higher_limits() {
+        // from 11-limit on, the vocabulary maps some ratios to more than one number of half steps,
+        // so that commas span every prime
+        for (prime_limit, rank) in [(7, 3), (11, 5), (13, 6)] {
+            let commas = comma_basis(&builtin_vocabulary(prime_limit));
+            assert_eq!(commas.len(), rank);
+            for comma in &commas {
+                assert_eq!(comma.get_interval_set().get_half_steps(), 0);
+                assert!(comma.get_freq_scale() > 1);
+                assert!(comma.cents() < 100.0);
+            }
+            for pair in commas.windows(2) {
+                assert!(pair[0].get_monzo() <= pair[1].get_monzo());
+            }
+        }
+        let commas = comma_basis(&builtin_vocabulary(7));
+        assert!(commas.iter().any(|comma| comma.get_monzo().get_prime_limit() == 7));
+    }
+
+    #[test]
+    fn runtime_vocabulary() {
+        // the two whole tones differ by the septimal comma
+        let vocabulary = vec![Interval::new("SeptimalWholeTone", Rational::from((8,7)), 2),
+                              Interval::new("WholeTone", Rational::from((9,8)), 2)];
+        let commas = comma_basis(&vocabulary);
+        assert_eq!(commas.len(), 1);
+        assert_eq!(commas[0].get_freq_scale(), Rational::from((64,63)));
+        assert_eq!(commas[0].get_name(), Some("septimal comma"));
+        assert_eq!(commas[0].get_interval_set().get_counts(), &[1, -1]);
+        // intervals that only combine to unisons do not form commas
+        let vocabulary = vec![Interval::new("Fifth", Rational::from((3,2)), 7),
+                              Interval::new("Ninth", Rational::from((9,4)), 14)];
+        assert!(comma_basis(&vocabulary).is_empty());
+        assert!(comma_basis(&[]).is_empty());
+        // intervals without half steps are commas themselves
+        let vocabulary = vec![Interval::new("Fifth", Rational::from((3,2)), 7),
+                              Interval::new("Diesis", Rational::from((128,125)), 0)];
+        assert_eq!(ratios(&comma_basis(&vocabulary)), vec![Rational::from((128,125))]);
+    }
+
+    #[test]
+    fn short_representations() {
+        // the counts of the commas are not blown up by the elimination
+        for prime_limit in [5, 7, 11, 13] {
+            for comma in comma_basis(&builtin_vocabulary(prime_limit)) {
+                assert!(comma.get_interval_set().num_intervals() <= 6);
+            }
+        }
+    }
+}
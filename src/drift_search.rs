@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use crate::error::Error;
+use crate::intervals::Interval;
+use crate::interval_set::IntervalSet;
+
+// small tolerance against rounding in the accumulated cents
+const CENTS_EPSILON: f64 = 1e-9;
+
+// largest number of distinct interval sets the search visits before giving up
+const MAX_NODES: usize = 1 << 20;
+
+// Interval set reached by the search, stored as the node it was reached from
+// and the interval (dimension, sign) that was added
+struct Node {
+    parent: usize,
+    idim: usize,
+    sign: i32
+}
+
+// Normals n of the facets n.x = 1 of the centrally symmetric polygon spanned by the points.
+// max |n.x| over the facets is the least number of intervals needed to reach x,
+// if fractional counts were allowed.
+fn polygon_facets(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut facets = Vec::new();
+    for (i, &(hi, di)) in points.iter().enumerate() {
+        for &(hj, dj) in &points[i+1..] {
+            let det = hi*dj - hj*di;
+            if det.abs() < CENTS_EPSILON {
+                continue;
+            }
+            for sign in [1.0, -1.0] {
+                let normal = ((dj - di*sign)/det, (hi*sign - hj)/det);
+                let is_facet = points.iter().all(|&(h, d)| (normal.0*h + normal.1*d).abs() <= 1.0 + CENTS_EPSILON);
+                let is_new = facets.iter().all(|&(nh, nd): &(f64, f64)| (nh - normal.0).abs() + (nd - normal.1).abs() > CENTS_EPSILON);
+                if is_facet && is_new {
+                    facets.push(normal);
+                }
+            }
+        }
+    }
+    facets
+}
+
+// Lower bound on the intervals needed to cover the half steps and the deviation within the allowed error
+fn min_intervals(facets: &[(f64, f64)], half_steps: i32, deviation: f64, max_err_cents: f64) -> f64 {
+    facets.iter()
+        .map(|&(nh, nd)| (nh*half_steps as f64 + nd*deviation).abs() - nd.abs()*max_err_cents)
+        .fold(0.0, f64::max)
+}
+
+// Interval sets that are not dominated in the number of intervals and the error in cents.
+// With increasing number of intervals the sets get closer to the target scaling by target_cents,
+// the last one is the first set within max_err_cents, if found within max_intervals intervals.
+// The breadth first search runs over the distinct combinations of half steps and ratios,
+// so that interval sets with equal effect are visited only once. Sets that cannot improve
+// on the closest set so far within max_intervals intervals are pruned by the bound of the relaxed problem.
+pub fn pareto_front(vocabulary: &[Interval], half_steps: i32, target_cents: f64,
+                    max_err_cents: f64, max_intervals: i32) -> Result<Vec<(IntervalSet, f64)>, Error> {
+    IntervalSet::new_with_hstep(half_steps, vocabulary)?;
+    let nprimes = vocabulary.iter()
+        .map(|interval| interval.get_monzo().get_exponents().len())
+        .max()
+        .unwrap_or(0);
+    let steps: Vec<Vec<i32>> = vocabulary.iter().map(|interval| {
+        let mut lattice = vec![interval.get_half_steps()];
+        lattice.extend(interval.get_monzo().get_exponents());
+        lattice.resize(nprimes+1, 0);
+        lattice
+    }).collect();
+    let deviations: Vec<f64> = vocabulary.iter()
+        .map(|interval| interval.get_monzo().cents() - 100.0*interval.get_half_steps() as f64)
+        .collect();
+    let points: Vec<(f64, f64)> = vocabulary.iter().zip(&deviations)
+        .map(|(interval, &deviation)| (interval.get_half_steps() as f64, deviation))
+        .collect();
+    let facets = polygon_facets(&points);
+    let target_deviation = target_cents - 100.0*half_steps as f64;
+
+    // the interval sets are identified by their half steps and prime exponents, the deviation
+    // is their difference in cents to the equal tempered half steps
+    let mut nodes = vec![Node {parent: 0, idim: 0, sign: 0}];
+    let mut visited: HashSet<Vec<i32>> = HashSet::from([vec![0; nprimes+1]]);
+    let mut level: Vec<(usize, Vec<i32>, f64)> = vec![(0, vec![0; nprimes+1], 0.0)];
+    let mut front: Vec<(IntervalSet, f64)> = Vec::new();
+    for depth in 0..=max_intervals {
+        // the node closest to the target among the ones with depth intervals
+        let best = level.iter()
+            .filter(|(_, lattice, _)| lattice[0] == half_steps)
+            .map(|(inode, _, deviation)| (*inode, (target_deviation - deviation).abs()))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((mut inode, err_cents)) = best {
+            if front.last().is_none_or(|(_, front_err)| err_cents < front_err - CENTS_EPSILON) {
+                let mut counts = vec![0; vocabulary.len()];
+                while inode != 0 {
+                    counts[nodes[inode].idim] += nodes[inode].sign;
+                    inode = nodes[inode].parent;
+                }
+                front.push((IntervalSet::new_from_vals(vocabulary, counts), err_cents));
+                if err_cents <= max_err_cents + CENTS_EPSILON {
+                    break;
+                }
+            }
+        }
+        // error that the following sets need to beat
+        let bound_err = front.last().map(|(_, front_err)| front_err - CENTS_EPSILON);
+        let mut next_level = Vec::new();
+        let mut lattice = vec![0; nprimes+1];
+        for (inode, node_lattice, node_deviation) in &level {
+            for (idim, step) in steps.iter().enumerate() {
+                for sign in [1, -1] {
+                    for ((value, node_value), step_value) in lattice.iter_mut().zip(node_lattice).zip(step) {
+                        *value = node_value + sign*step_value;
+                    }
+                    // sets pruned below are pruned again when reached with more intervals,
+                    // so they count as visited as well
+                    if visited.contains(&lattice) {
+                        continue;
+                    }
+                    visited.insert(lattice.clone());
+                    let deviation = node_deviation + sign as f64*deviations[idim];
+                    if let Some(bound_err) = bound_err {
+                        let remaining = min_intervals(&facets, half_steps - lattice[0], target_deviation - deviation, bound_err);
+                        if (depth+1) as f64 + remaining > max_intervals as f64 + CENTS_EPSILON {
+                            continue;
+                        }
+                    }
+                    next_level.push((nodes.len(), lattice.clone(), deviation));
+                    nodes.push(Node {parent: *inode, idim, sign});
+                }
+            }
+            if visited.len() > MAX_NODES {
+                return Err(Error::SearchLimitExceeded {visited: MAX_NODES, half_steps, target: Some((target_cents, max_err_cents))});
+            }
+        }
+        level = next_level;
+    }
+    Ok(front)
+}
+
+// Finds the interval set with the fewest intervals that spans the half steps exactly
+// and scales the frequency by target_cents within max_err_cents.
+pub fn search_drift(vocabulary: &[Interval], half_steps: i32, target_cents: f64,
+                    max_err_cents: f64, max_intervals: i32) -> Result<IntervalSet, Error> {
+    match pareto_front(vocabulary, half_steps, target_cents, max_err_cents, max_intervals)?.pop() {
+        Some((interval_set, err_cents)) if err_cents <= max_err_cents + CENTS_EPSILON => Ok(interval_set),
+        _ => Err(Error::UnreachableTarget {half_steps, target_cents, max_err_cents, max_intervals})
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rug::Rational;
+    use crate::interval_vocabulary::builtin_vocabulary;
+    use crate::commas::comma_basis;
+
+    fn cents(interval_set: &IntervalSet) -> f64 {
+        interval_set.get_monzo().cents()
+    }
+
+    #[test]
+    fn hit_comma() {
+        let vocabulary = builtin_vocabulary(5);
+        // the syntonic comma needs two intervals
+        let interval_set = search_drift(&vocabulary, 0, 21.506, 0.01, 10).unwrap();
+        assert_eq!(interval_set.get_freq_scale(), Rational::from((81,80)));
+        assert_eq!(interval_set.num_intervals(), 2);
+        let interval_set = search_drift(&vocabulary, 0, -21.506, 0.01, 10).unwrap();
+        assert_eq!(interval_set.get_freq_scale(), Rational::from((80,81)));
+        // every comma of the basis is found with at most as many intervals as in the basis
+        for prime_limit in [3, 5, 7] {
+            let vocabulary = builtin_vocabulary(prime_limit);
+            for comma in comma_basis(&vocabulary) {
+                let interval_set = search_drift(&vocabulary, 0, comma.cents(), 1e-6, 20).unwrap();
+                assert_eq!(interval_set.get_freq_scale(), comma.get_freq_scale());
+                assert!(interval_set.num_intervals() <= comma.get_interval_set().num_intervals());
+            }
+        }
+    }
+
+    #[test]
+    fn no_drift() {
+        // without drift the half step solution is optimal
+        for prime_limit in [5, 7, 13] {
+            let vocabulary = builtin_vocabulary(prime_limit);
+            for half_steps in [-7, 0, 5, 12] {
+                let interval_set = search_drift(&vocabulary, half_steps, 100.0*half_steps as f64, 50.0, 20).unwrap();
+                assert_eq!(interval_set.num_intervals(),
+                           IntervalSet::new_with_hstep(half_steps, &vocabulary).unwrap().num_intervals());
+            }
+        }
+    }
+
+    #[test]
+    fn minimal_count() {
+        for (prime_limit, half_steps, target_cents, max_err_cents) in [(5, 0, 84.467, 1.0), (5, 5, 520.0, 1.0),
+                                                                      (7, 7, 750.0, 0.5), (13, -12, -1100.0, 1.0)] {
+            let vocabulary = builtin_vocabulary(prime_limit);
+            let interval_set = search_drift(&vocabulary, half_steps, target_cents, max_err_cents, 30).unwrap();
+            assert_eq!(interval_set.get_half_steps(), half_steps);
+            assert!((cents(&interval_set) - target_cents).abs() <= max_err_cents);
+            // no set with fewer intervals satisfies the constraints
+            let nintervals = interval_set.num_intervals() as i32;
+            let shorter = IntervalSet::all_with_hstep(half_steps, &vocabulary, nintervals-1).unwrap();
+            assert!(shorter.iter().all(|other| (cents(other) - target_cents).abs() > max_err_cents));
+        }
+    }
+
+    #[test]
+    fn large_drift() {
+        // a minor third of drift without changing the half steps
+        let vocabulary = builtin_vocabulary(5);
+        let interval_set = search_drift(&vocabulary, 0, 300.0, 5.0, 64).unwrap();
+        assert_eq!(interval_set.get_half_steps(), 0);
+        assert!((cents(&interval_set) - 300.0).abs() <= 5.0);
+        assert_eq!(interval_set.num_intervals(), 19);
+        // the closest set is chosen among the shortest ones
+        let interval_set = search_drift(&vocabulary, 0, 21.0, 25.0, 10).unwrap();
+        assert_eq!(interval_set, IntervalSet::new_from_vals(&vocabulary, vec![0; vocabulary.len()]));
+        let interval_set = search_drift(&vocabulary, 0, 21.0, 1.0, 10).unwrap();
+        assert_eq!(interval_set.get_freq_scale(), Rational::from((81,80)));
+    }
+
+    #[test]
+    fn search_bound() {
+        let vocabulary = builtin_vocabulary(5);
+        let result = search_drift(&vocabulary, 0, 84.467, 1.0, 3);
+        assert_eq!(result, Err(Error::UnreachableTarget {half_steps: 0, target_cents: 84.467, max_err_cents: 1.0, max_intervals: 3}));
+        // unreachable half steps are reported before searching
+        let vocabulary = vec![Interval::new("WholeTone", Rational::from((9,8)), 2),
+                              Interval::new("MinorWholeTone", Rational::from((10,9)), 2)];
+        assert_eq!(search_drift(&vocabulary, 1, 100.0, 1.0, 10), Err(Error::UnreachableHalfSteps {half_steps: 1, multiple: 2}));
+        // vocabularies without commas cannot drift
+        let vocabulary = vec![Interval::new("Fifth", Rational::from((3,2)), 7)];
+        assert!(search_drift(&vocabulary, 0, 10.0, 1.0, 50).is_err());
+        assert_eq!(search_drift(&vocabulary, 14, 1403.91, 0.01, 50),
+                   Ok(IntervalSet::new_from_vals(&vocabulary, vec![2])));
+    }
+
+    #[test]
+    fn front() {
+        for (prime_limit, half_steps, target_cents) in [(5, 0, 84.467), (5, 7, 690.0), (7, 7, 750.0)] {
+            let vocabulary = builtin_vocabulary(prime_limit);
+            let front = pareto_front(&vocabulary, half_steps, target_cents, 0.5, 30).unwrap();
+            // the front starts with the shortest set and ends within the allowed error
+            assert_eq!(front[0].0.num_intervals(),
+                       IntervalSet::new_with_hstep(half_steps, &vocabulary).unwrap().num_intervals());
+            assert!(front.last().unwrap().1 <= 0.5);
+            assert_eq!(front.last().unwrap().0,
+                       search_drift(&vocabulary, half_steps, target_cents, 0.5, 30).unwrap());
+            for pair in front.windows(2) {
+                assert!(pair[0].0.num_intervals() < pair[1].0.num_intervals());
+                assert!(pair[0].1 > pair[1].1);
+            }
+            // no set is closer to the target than the front with as many intervals
+            for (interval_set, err_cents) in &front {
+                assert_eq!(interval_set.get_half_steps(), half_steps);
+                assert!((cents(interval_set) - target_cents).abs() - err_cents < 1e-9);
+                let nintervals = interval_set.num_intervals() as i32;
+                for other in IntervalSet::all_with_hstep(half_steps, &vocabulary, nintervals.min(5)).unwrap() {
+                    let other_err = (cents(&other) - target_cents).abs();
+                    let front_err = front.iter()
+                        .filter(|(front_set, _)| front_set.num_intervals() <= other.num_intervals())
+                        .map(|(_, front_err)| *front_err)
+                        .next_back()
+                        .unwrap();
+                    assert!(other_err >= front_err - 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_front() {
+        // without reaching the allowed error the front extends to the largest number of intervals
+        let vocabulary = builtin_vocabulary(5);
+        let front = pareto_front(&vocabulary, 0, 1200.0, 1.0, 10).unwrap();
+        assert!(front.last().unwrap().1 > 1.0);
+        assert!(front.last().unwrap().0.num_intervals() <= 10);
+        assert_eq!(front[0].0.num_intervals(), 0);
+        assert_eq!(front[0].1, 1200.0);
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+
+// octaves a note name may be given in, the range of MIDI notes
+pub const MIN_OCTAVE: i32 = -1;
+pub const MAX_OCTAVE: i32 = 9;
+
+// Errors of the note parsing, the interval definitions and the sequence searches
+#[derive(Debug, PartialEq, Clone)]
+pub enum Error {
+    // note name that is not one of C, C#, Db, ..., B#
+    InvalidNoteName(String),
+    // note without octave, e.g. A instead of A4
+    MissingOctave(String),
+    // octave of a note that is not an integer
+    InvalidOctave(String),
+    // octave outside of MIN_OCTAVE to MAX_OCTAVE
+    OctaveOutOfRange(i32),
+    // target frequency scaling that cannot be parsed or contradicts other options
+    InvalidTarget(String),
+    // command line option value that is not one of its choices
+    InvalidOption(String),
+    // concert pitch that cannot be parsed
+    InvalidReference(String),
+    // ratio that cannot be represented as monzo
+    InvalidRatio(String),
+    // interval of a vocabulary with the reason why it is rejected
+    InvalidInterval {name: String, reason: String},
+    // vocabulary that cannot be read or cannot be used by the searches
+    InvalidVocabulary(String),
+    // half steps that are not a multiple of the half step sizes of the vocabulary
+    UnreachableHalfSteps {half_steps: i32, multiple: i32},
+    // no interval set reaches the target within the allowed number of intervals
+    UnreachableTarget {half_steps: i32, target_cents: f64, max_err_cents: f64, max_intervals: i32},
+    // no order of the intervals keeps the notes within the ranges of the instruments
+    OutOfRange(String),
+    // the search visited too many interval sets before reaching the target cents within the allowed error,
+    // or before listing the sets spanning the half steps when there is no target
+    SearchLimitExceeded {visited: usize, half_steps: i32, target: Option<(f64, f64)>},
+    // the range search tried too many orders before finding one within the ranges
+    RangeSearchLimitExceeded(String),
+    // sequence that cannot be written to an output file
+    Export(String)
+}
+
+impl Error {
+    // exit code of the command line tool: 1 for failed exports, 2 for invalid input,
+    // 3 for targets without solution or playable order and 4 for exceeded search limits
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Export(_) => 1,
+            Error::UnreachableHalfSteps {..} | Error::UnreachableTarget {..} | Error::OutOfRange(_) => 3,
+            Error::SearchLimitExceeded {..} | Error::RangeSearchLimitExceeded(_) => 4,
+            _ => 2
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidNoteName(name) =>
+                write!(f, "Invalid note name '{}', expected one of C, C#, Db, D, ..., Bb, B", name),
+            Error::MissingOctave(note) =>
+                write!(f, "Missing octave in note name '{}'", note),
+            Error::InvalidOctave(note) =>
+                write!(f, "Invalid octave in note name '{}'", note),
+            Error::OctaveOutOfRange(octave) =>
+                write!(f, "Octave {} is outside of the range {} to {}", octave, MIN_OCTAVE, MAX_OCTAVE),
+            Error::InvalidTarget(message) | Error::InvalidOption(message) | Error::InvalidReference(message) | Error::InvalidRatio(message) | Error::InvalidVocabulary(message) | Error::OutOfRange(message) | Error::RangeSearchLimitExceeded(message) | Error::Export(message) =>
+                write!(f, "{}", message),
+            Error::InvalidInterval {name, reason} =>
+                write!(f, "Interval '{}': {}", name, reason),
+            Error::UnreachableHalfSteps {multiple: 0, ..} =>
+                write!(f, "The interval vocabulary can only reach 0 half steps"),
+            Error::UnreachableHalfSteps {multiple, ..} =>
+                write!(f, "The interval vocabulary can only reach multiples of {} half steps", multiple),
+            Error::UnreachableTarget {half_steps, target_cents, max_err_cents, max_intervals} =>
+                write!(f, "No interval set with at most {} intervals spans {} half steps and scales by {:.3} cents within {} cents",
+                       max_intervals, half_steps, target_cents, max_err_cents),
+            Error::SearchLimitExceeded {visited, half_steps, target: Some((target_cents, max_err_cents))} =>
+                write!(f, "Gave up after visiting {} interval sets without spanning {} half steps and scaling by {:.3} cents within {} cents",
+                       visited, half_steps, target_cents, max_err_cents),
+            Error::SearchLimitExceeded {visited, half_steps, target: None} =>
+                write!(f, "Gave up after visiting {} interval sets while listing the sets spanning {} half steps",
+                       visited, half_steps)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        assert_eq!(Error::OctaveOutOfRange(12).to_string(), "Octave 12 is outside of the range -1 to 9");
+        assert_eq!(Error::InvalidInterval {name: String::from("a"), reason: String::from("ratio 1 is a unison")}.to_string(),
+                   "Interval 'a': ratio 1 is a unison");
+        assert_eq!(Error::UnreachableHalfSteps {half_steps: 1, multiple: 0}.to_string(),
+                   "The interval vocabulary can only reach 0 half steps");
+        assert_eq!(Error::UnreachableHalfSteps {half_steps: 1, multiple: 2}.to_string(),
+                   "The interval vocabulary can only reach multiples of 2 half steps");
+        assert_eq!(Error::UnreachableTarget {half_steps: 0, target_cents: 84.4671, max_err_cents: 1.0, max_intervals: 3}.to_string(),
+                   "No interval set with at most 3 intervals spans 0 half steps and scales by 84.467 cents within 1 cents");
+        assert_eq!(Error::SearchLimitExceeded {visited: 10, half_steps: 1, target: None}.to_string(),
+                   "Gave up after visiting 10 interval sets while listing the sets spanning 1 half steps");
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(Error::Export(String::from("Cannot write MIDI file")).exit_code(), 1);
+        assert_eq!(Error::InvalidNoteName(String::from("H")).exit_code(), 2);
+        assert_eq!(Error::InvalidVocabulary(String::from("The interval vocabulary is empty")).exit_code(), 2);
+        assert_eq!(Error::UnreachableHalfSteps {half_steps: 1, multiple: 2}.exit_code(), 3);
+        assert_eq!(Error::OutOfRange(String::from("No order of the 2 intervals keeps the notes within C4..F4")).exit_code(), 3);
+        assert_eq!(Error::SearchLimitExceeded {visited: 10, half_steps: 0, target: Some((1.0, 0.1))}.exit_code(), 4);
+        assert_eq!(Error::RangeSearchLimitExceeded(String::from("Gave up after trying 10 orders")).exit_code(), 4);
+    }
+}
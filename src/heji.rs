@@ -0,0 +1,57 @@
+use crate::monzo::{Monzo, nth_prime};
+
+// Signs of the Helmholtz-Ellis accidentals in text form for the primes 5 to 13, each raising
+// or lowering the Pythagorean note by the comma of the prime. A positive exponent lowers
+// for 5 (5/4 is E lowered by 81/80), 7 (7/4 is Bb lowered by 64/63) and 13 (13/8 is A lowered
+// by 27/26) and raises for 11 (11/8 is F raised by 33/32).
+const SIGNS: [(u32, &str, &str); 4] = [
+    (5, "v", "^"),
+    (7, "L", "7"),
+    (11, "^11", "v11"),
+    (13, "v13", "^13"),
+];
+
+// Comma accidentals of the ratio, one sign per prime factor, e.g. "v" for 5/4 and "vL" for 35/32.
+// Primes above 13 have no sign and are left out.
+pub fn heji_accidentals(monzo: &Monzo) -> String {
+    let mut accidentals = String::new();
+    for (iprime, &exponent) in monzo.get_exponents().iter().enumerate() {
+        let prime = nth_prime(iprime);
+        if let Some((_, positive, negative)) = SIGNS.iter().find(|(sign_prime, _, _)| *sign_prime == prime) {
+            let sign = if exponent > 0 {positive} else {negative};
+            accidentals += &sign.repeat(exponent.unsigned_abs() as usize);
+        }
+    }
+    accidentals
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rug::Rational;
+
+    fn heji(numer: u32, denom: u32) -> String {
+        heji_accidentals(&Monzo::try_from(&Rational::from((numer, denom))).unwrap())
+    }
+
+    #[test]
+    fn pythagorean() {
+        assert_eq!(heji(1, 1), "");
+        assert_eq!(heji(3, 2), "");
+        assert_eq!(heji(531441, 524288), "");
+    }
+
+    #[test]
+    fn commas() {
+        assert_eq!(heji(5, 4), "v");
+        assert_eq!(heji(6, 5), "^");
+        assert_eq!(heji(25, 16), "vv");
+        assert_eq!(heji(7, 4), "L");
+        assert_eq!(heji(8, 7), "7");
+        assert_eq!(heji(35, 32), "vL");
+        assert_eq!(heji(11, 8), "^11");
+        assert_eq!(heji(13, 8), "v13");
+        assert_eq!(heji(16, 13), "^13");
+        assert_eq!(heji(17, 16), "");
+    }
+}
@@ -1,66 +1,152 @@
 use rug::Rational;
 use crate::Note;
-use crate::just_intervals::JustInterval;
+use crate::error::Error;
+use crate::intervals::Interval;
+use crate::monzo::Monzo;
+use crate::heji::heji_accidentals;
+use crate::reference::Reference;
 
 #[derive(Debug, PartialEq)]
 pub struct IntervalSequence {
-    pub intervals: Vec<JustInterval>,
+    pub intervals: Vec<Interval>,
     pub half_steps: i32,
-    pub freq_scale: Rational,    
+    pub monzo: Monzo,
+}
+
+// Note of a sequence together with its exact ratio to the starting note
+#[derive(Debug, PartialEq, Clone)]
+pub struct SequenceNote {
+    pub note: Note,
+    pub monzo: Monzo,
+    // cents the note lies above its equal tempered pitch, the starting note being in tune
+    pub deviation_cents: f64,
+    // frequency in Hz reached by the just intervals from the equal tempered starting note
+    pub just_freq: f64,
+    // frequency in Hz of the note in 12-TET
+    pub tempered_freq: f64,
+}
+
+impl SequenceNote {
+    pub fn get_freq_scale(&self) -> Rational {
+        self.monzo.to_rational()
+    }
+
+    // Helmholtz-Ellis comma accidentals of the ratio to the starting note
+    pub fn heji(&self) -> String {
+        heji_accidentals(&self.monzo)
+    }
 }
 
 impl IntervalSequence {
     pub fn new() -> Self {
         IntervalSequence {
-            intervals: Vec::<JustInterval>::new(),
+            intervals: Vec::<Interval>::new(),
             half_steps: 0,
-            freq_scale: Rational::from((1,1))
+            monzo: Monzo::unison()
         }
     }
 
-    #[cfg(test)]
-    pub fn add_interval(&mut self, interval: JustInterval) {
-        self.freq_scale *= interval.get_freq_scale();
+    pub fn add_interval<I: Into<Interval>>(&mut self, interval: I) {
+        let interval = interval.into();
+        self.monzo = &self.monzo * &interval.get_monzo();
         self.half_steps += interval.get_half_steps();
         self.intervals.push(interval);
     }
 
+    // sequence of the intervals in the given order, as the tests build them
     #[cfg(test)]
+    pub(crate) fn from_intervals<I: Into<Interval>>(intervals: impl IntoIterator<Item = I>) -> Self {
+        let mut sequence = IntervalSequence::new();
+        for interval in intervals {
+            sequence.add_interval(interval);
+        }
+        sequence
+    }
+
     pub fn get_half_steps(&self) -> i32 {
         self.half_steps
     }
 
-    #[cfg(test)]
     pub fn get_freq_scale(&self) -> Rational {
-        self.freq_scale.clone()
+        self.monzo.to_rational()
     }
 
-    pub fn to_notes(&self, startingnote: Note) -> Vec<Note> {
+    // fails when a note leaves the octaves of the note names
+    pub fn to_notes(&self, startingnote: Note) -> Result<Vec<Note>, Error> {
+        self.spelled_notes(startingnote, Note::shift_by_interval)
+    }
+
+    // notes of the sequence with the accumulated ratios and their drift from 12-TET
+    pub fn to_annotated_notes(&self, startingnote: Note, reference: &Reference) -> Result<Vec<SequenceNote>, Error> {
+        Ok(self.annotate(self.to_notes(startingnote)?, reference))
+    }
+
+    // annotated notes spelled without respelling, to which the HEJI signs of their ratios apply
+    pub fn to_heji_notes(&self, startingnote: Note, reference: &Reference) -> Result<Vec<SequenceNote>, Error> {
+        Ok(self.annotate(self.spelled_notes(startingnote, Note::shift_by_interval_unrespelled)?, reference))
+    }
+
+    fn spelled_notes(&self, startingnote: Note, shift: fn(&Note, &Interval) -> Result<Note, Error>) -> Result<Vec<Note>, Error> {
         let mut note_sequence = Vec::new();
         note_sequence.push(startingnote);
         let mut last_note = startingnote;
 
         for iinterval in self.intervals.iter() {
-            let shifted_note = last_note.shift_by_interval(iinterval.clone());
+            let shifted_note = shift(&last_note, iinterval)?;
             last_note = shifted_note;
             note_sequence.push(shifted_note);
         }
-        note_sequence
+        Ok(note_sequence)
     }
+
+    fn annotate(&self, notes: Vec<Note>, reference: &Reference) -> Vec<SequenceNote> {
+        let starting_freq = reference.tempered_freq(&notes[0]);
+        let mut monzo = Monzo::unison();
+        let mut half_steps = 0;
+        let mut annotated = Vec::with_capacity(self.intervals.len()+1);
+        for (inote, note) in notes.into_iter().enumerate() {
+            if inote > 0 {
+                let interval = &self.intervals[inote-1];
+                monzo = &monzo * &interval.get_monzo();
+                half_steps += interval.get_half_steps();
+            }
+            let deviation_cents = monzo.cents() - 100.0*half_steps as f64;
+            let just_freq = starting_freq * monzo.to_rational().to_f64();
+            let tempered_freq = reference.tempered_freq(&note);
+            annotated.push(SequenceNote {note, monzo: monzo.clone(), deviation_cents, just_freq, tempered_freq});
+        }
+        annotated
+    }
+}
+
+impl Default for IntervalSequence {
+    fn default() -> Self {
+        IntervalSequence::new()
+    }
+}
+
+// notes along the intervals from the starting note, as the tests of the exports write them.
+// The exports do not depend on the reference.
+#[cfg(test)]
+pub(crate) fn test_notes(intervals: &[crate::just_intervals::JustInterval], startingnote: Note) -> Vec<SequenceNote> {
+    IntervalSequence::from_intervals(intervals.iter().copied())
+        .to_annotated_notes(startingnote, &Reference::default())
+        .unwrap()
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::just_intervals::JustInterval;
 
     #[test]
     fn add_interval() {
         let mut seq = IntervalSequence::new();
         seq.add_interval(JustInterval::MajorThird);
         let ref_seq = IntervalSequence {
-            intervals: vec![JustInterval::MajorThird],
+            intervals: vec![JustInterval::MajorThird.into()],
             half_steps: 4,
-            freq_scale: Rational::from((5,4))
+            monzo: Monzo::new(vec![-2, 0, 1])
         };
         assert_eq!(seq, ref_seq);
 
@@ -71,15 +157,16 @@ mod test {
         seq.add_interval(JustInterval::IPerfectFifth);
         seq.add_interval(JustInterval::IPerfectFifth);
         let ref_seq = IntervalSequence {
-            intervals: vec![JustInterval::MajorThird,
-                            JustInterval::PerfectFourth,
-                            JustInterval::PerfectFourth,
-                            JustInterval::IPerfectFifth,
-                            JustInterval::IPerfectFifth],
+            intervals: vec![JustInterval::MajorThird.into(),
+                            JustInterval::PerfectFourth.into(),
+                            JustInterval::PerfectFourth.into(),
+                            JustInterval::IPerfectFifth.into(),
+                            JustInterval::IPerfectFifth.into()],
             half_steps: 0,
-            freq_scale: Rational::from((80,81))
+            monzo: Monzo::new(vec![4, -4, 1])
         };
         assert_eq!(seq, ref_seq);
+        assert_eq!(seq.get_freq_scale(), Rational::from((80,81)));
     }
 
     #[test]
@@ -90,7 +177,61 @@ mod test {
         sequence.add_interval(JustInterval::IPerfectFourth);
         sequence.add_interval(JustInterval::PerfectFifth);
     
-        let note_sequence = sequence.to_notes(startingnote);
+        let note_sequence = sequence.to_notes(startingnote).unwrap();
         assert_eq!(note_sequence, vec![Note::new("C", 3), Note::new("E", 3), Note::new("B", 2), Note::new("F#", 3)]);
+        // two octaves up from C9 leave the octaves of the note names
+        let sequence = IntervalSequence::from_intervals([JustInterval::PerfectFifth, JustInterval::PerfectFourth,
+                                                         JustInterval::PerfectFifth, JustInterval::PerfectFourth]);
+        assert_eq!(sequence.to_notes(Note::new("C", 9)), Err(Error::OctaveOutOfRange(10)));
+        assert_eq!(sequence.to_annotated_notes(Note::new("C", 9), &Reference::default()), Err(Error::OctaveOutOfRange(10)));
+    }
+
+    #[test]
+    fn annotated_notes() {
+        let mut sequence = IntervalSequence::new();
+        sequence.add_interval(JustInterval::MajorWholeTone);
+        sequence.add_interval(JustInterval::IMinorWholeTone);
+        sequence.add_interval(JustInterval::HarmonicSeventh);
+        let reference: Reference = "C3=130".parse().unwrap();
+        let notes = sequence.to_annotated_notes(Note::new("C", 3), &reference).unwrap();
+        assert_eq!(notes.len(), 4);
+        assert_eq!(notes[0], SequenceNote {note: Note::new("C", 3), monzo: Monzo::unison(), deviation_cents: 0.0,
+                                           just_freq: 130.0, tempered_freq: 130.0});
+        assert_eq!(notes[1].note, Note::new("D", 3));
+        assert_eq!(notes[1].get_freq_scale(), Rational::from((9,8)));
+        assert!((notes[1].deviation_cents - 3.910).abs() < 1e-3);
+        assert_eq!(notes[1].heji(), "");
+        assert!((notes[1].just_freq - 146.25).abs() < 1e-9);
+        assert!((notes[1].tempered_freq - 130.0*2.0f64.powf(2.0/12.0)).abs() < 1e-9);
+        // the syntonic comma leaves the letter and raises the pitch
+        assert_eq!(notes[2].note, Note::new("C", 3));
+        assert_eq!(notes[2].get_freq_scale(), Rational::from((81,80)));
+        assert!((notes[2].deviation_cents - 21.506).abs() < 1e-3);
+        assert_eq!(notes[2].heji(), "^");
+        assert!((notes[2].just_freq - 131.625).abs() < 1e-9);
+        assert_eq!(notes[2].tempered_freq, 130.0);
+        assert_eq!(notes[3].note, Note::new("Bb", 3));
+        assert_eq!(notes[3].get_freq_scale(), Rational::from((567,320)));
+        assert!((notes[3].deviation_cents - (21.506 - 31.174)).abs() < 1e-3);
+        assert_eq!(notes[3].heji(), "^L");
+        // the deviation is the ratio of both frequencies
+        assert!((1200.0*(notes[3].just_freq/notes[3].tempered_freq).log2() - notes[3].deviation_cents).abs() < 1e-9);
+    }
+
+    #[test]
+    fn heji_notes() {
+        // five major thirds pile up to F###, which is respelled to G# a Pythagorean comma lower
+        let sequence = IntervalSequence::from_intervals([JustInterval::MajorThird; 5]);
+        let respelled = sequence.to_annotated_notes(Note::new("C", 4), &Reference::default()).unwrap();
+        assert_eq!(respelled[5].note, Note::new("G#", 5));
+        let notes = sequence.to_heji_notes(Note::new("C", 4), &Reference::default()).unwrap();
+        assert_eq!(notes.iter().map(|note| note.note.to_string().trim().to_string()).collect::<Vec<_>>(),
+                   vec!["C4", "E4", "G#4", "B#4", "Dx5", "F#x5"]);
+        assert_eq!(notes[5].heji(), "vvvvv");
+        // only the spelling differs
+        for (note, other) in notes.iter().zip(&respelled) {
+            assert!(note.note.is_enharmonic(&other.note));
+            assert_eq!((note.just_freq, note.deviation_cents), (other.just_freq, other.deviation_cents));
+        }
     }
 }
@@ -1,203 +1,557 @@
-use itertools::Itertools;
+use std::ops::{AddAssign,SubAssign,Neg,Mul};
 use num_traits::Pow;
 use rug::Rational;
-use crate::just_intervals::JustInterval;
+use crate::error::Error;
+use crate::intervals::Interval;
+use crate::monzo::Monzo;
 use crate::interval_sequences::IntervalSequence;
-use crate::vector_distributor::distribute;
+use crate::notes::Note;
+use crate::note_range::{NoteRange, order_in_range};
+use crate::vector_distributor::{SequenceOrder, order};
 
+// count vectors the listing of the half step solutions may visit before giving up
+const MAX_HSTEP_VISITED: usize = 1 << 22;
+// largest number of intervals of the listed half step solutions
+pub const MAX_LISTED_INTERVALS: i32 = 16;
+
+// Vector of interval counts over an arbitrary basis of intervals
 #[derive(Debug, PartialEq, Clone)]
 pub struct IntervalSet {
-    n_major_third: i32,
-    n_perfect_fourth: i32,
-    n_perfect_fifth: i32
+    // intervals spanning the vector space
+    basis: Vec<Interval>,
+    // number of each basis interval, negative counts denote the inverted interval
+    counts: Vec<i32>
 }
 
-impl IntervalSet {
-    pub fn new_empty() -> Self {
-        IntervalSet::new_from_vals(0, 0, 0)
-    }
-
-    pub fn new_from_vals(n_major_third: i32, n_perfect_fourth: i32, n_perfect_fifth: i32) -> Self {
-        Self {n_major_third, n_perfect_fourth, n_perfect_fifth}
-    }
-
-    pub fn new_with_hstep(half_steps: i32) -> Self {
-        let mut interval_set = IntervalSet::new_empty();
-        'limit: for maxval in 0.. {
-            // construct an iterator that goes 0, 1, -1, 2, -2, ...
-            let signed_iterator = (-maxval+1..1).rev().interleave(1..maxval);
-            // the third counter is always positive in order to prohibit simple inversions of sets
-            for ithird in signed_iterator.clone() {
-                for ifourth in signed_iterator.clone() {
-                    for ififth in signed_iterator.clone() {
-                        let total_half_steps = JustInterval::MajorThird.get_half_steps() * ithird
-                                             + JustInterval::PerfectFourth.get_half_steps() * ifourth
-                                             + JustInterval::PerfectFifth.get_half_steps() * ififth;
-                        if total_half_steps == half_steps {
-                            interval_set.n_major_third = ithird;
-                            interval_set.n_perfect_fourth = ifourth;
-                            interval_set.n_perfect_fifth = ififth;
-                            break 'limit;
-                        }
+fn gcd(a: i32, b: i32) -> i32 {
+    match b {
+        0 => a.abs(),
+        _ => gcd(b, a % b)
+    }
+}
+
+// Checks that the half step sizes of the basis can be combined to the requested number of half steps
+fn check_reachable(half_steps: i32, basis: &[Interval]) -> Result<(), Error> {
+    let gcd = basis.iter()
+        .fold(0, |acc, interval| gcd(acc, interval.get_half_steps()));
+    match gcd {
+        0 if half_steps != 0 => Err(Error::UnreachableHalfSteps {half_steps, multiple: 0}),
+        0 => Ok(()),
+        _ if half_steps % gcd != 0 => Err(Error::UnreachableHalfSteps {half_steps, multiple: gcd}),
+        _ => Ok(())
+    }
+}
+
+// Solves sum(counts[i]*half_steps[i]) == half_steps with the smallest number of intervals.
+// Let L be the largest interval with |L| half steps. Every set of |L| or more smaller intervals
+// contains a subset whose half steps are a multiple of |L| and that can be replaced by
+// at most as many L. Thus a shortest solution exists with less than |L| smaller intervals,
+// which can only add up to offsets r with |r| < |L|*|L|. A breadth first search finds the
+// shortest way to every offset, and the remaining half_steps - r are covered by L.
+fn solve_half_steps(half_steps: i32, basis: &[Interval]) -> Vec<i32> {
+    let mut counts = vec![0; basis.len()];
+    let ilarge = match (0..basis.len()).max_by_key(|&idim| (basis[idim].get_half_steps().abs(), basis.len()-idim)) {
+        Some(ilarge) if basis[ilarge].get_half_steps() != 0 => ilarge,
+        _ => return counts
+    };
+    let large_steps = basis[ilarge].get_half_steps();
+    let max_depth = large_steps.abs() - 1;
+    let max_offset = max_depth*large_steps.abs();
+    let width = (2*max_offset + 1) as usize;
+    // number of small intervals to reach an offset and the last interval (dimension, sign) on the way
+    let mut depths: Vec<Option<i32>> = vec![None; width];
+    let mut last_steps: Vec<(usize, i32)> = vec![(0, 0); width];
+    depths[max_offset as usize] = Some(0);
+    let mut front = vec![0];
+    for depth in 1..=max_depth {
+        let mut next_front = Vec::new();
+        for offset in front {
+            for (idim, interval) in basis.iter().enumerate() {
+                if idim == ilarge || interval.get_half_steps() == 0 {
+                    continue;
+                }
+                for sign in [1, -1] {
+                    let next = offset + sign*interval.get_half_steps();
+                    let inext = (next + max_offset) as usize;
+                    if next.abs() <= max_offset && depths[inext].is_none() {
+                        depths[inext] = Some(depth);
+                        last_steps[inext] = (idim, sign);
+                        next_front.push(next);
                     }
                 }
             }
         }
-        interval_set
-    }
-
-    pub fn new_with_freq_scale() -> (Self, Self) {
-        let mut interval_sets = (IntervalSet::new_empty(), IntervalSet::new_empty());
-        // search for the downcaling set
-        'limit: for maxval in 0.. {
-            // construct an iterator that goes 0, 1, -1, 2, -2, ...
-            let signed_iterator = (-maxval+1..1).rev().interleave(1..maxval);
-            for ithird in signed_iterator.clone() {
-                for ifourth in signed_iterator.clone() {
-                    for ififth in signed_iterator.clone() {
-                        // skip the empty set
-                        if ithird == 0 && ifourth == 0 && ififth == 0 {
-                            continue;
-                        }
-                        let total_half_steps = JustInterval::MajorThird.get_half_steps() * ithird
-                                             + JustInterval::PerfectFourth.get_half_steps() * ifourth
-                                             + JustInterval::PerfectFifth.get_half_steps() * ififth;
-                        let total_freq_scale = JustInterval::MajorThird.get_freq_scale().pow(ithird)
-                                             * JustInterval::PerfectFourth.get_freq_scale().pow(ifourth)
-                                             * JustInterval::PerfectFifth.get_freq_scale().pow(ififth);
-
-                        if total_half_steps == 0 &&
-                           total_freq_scale < 1 {
-                            interval_sets.0.n_major_third = ithird;
-                            interval_sets.0.n_perfect_fourth = ifourth;
-                            interval_sets.0.n_perfect_fifth = ififth;
-                            break 'limit;
-                        }
-                    }
-                }
+        front = next_front;
+    }
+    // pick the offset that needs the fewest intervals in total, in i64 as the half steps may be close to the i32 limits
+    let (half_steps, large) = (i64::from(half_steps), i64::from(large_steps));
+    let best = (-max_offset..=max_offset)
+        .filter(|&offset| (half_steps - i64::from(offset)) % large == 0)
+        .filter_map(|offset| {
+            depths[(offset + max_offset) as usize]
+                .map(|depth| (i64::from(depth) + ((half_steps - i64::from(offset))/large).abs(), offset.abs(), offset))
+        })
+        .min();
+    if let Some((_, _, mut offset)) = best {
+        counts[ilarge] = ((half_steps - i64::from(offset))/large) as i32;
+        while offset != 0 {
+            let (idim, sign) = last_steps[(offset + max_offset) as usize];
+            counts[idim] += sign;
+            offset -= sign*basis[idim].get_half_steps();
+        }
+    }
+    counts
+}
+
+// Collects every count vector with at most max_intervals intervals that matches the half steps.
+// max_steps[idim] is the largest half step size among the remaining dimensions, which bounds
+// the distance that can still be covered. The number of count vectors grows exponentially
+// with the dimensions, so the search gives up after MAX_HSTEP_VISITED of them.
+struct HalfStepSearch<'a> {
+    basis: &'a [Interval],
+    max_steps: Vec<i64>,
+    found: Vec<Vec<i32>>,
+    visited: usize
+}
+
+impl HalfStepSearch<'_> {
+    fn collect(&mut self, counts: &mut [i32], idim: usize, half_steps: i64, max_intervals: i32) -> bool {
+        self.visited += 1;
+        if self.visited > MAX_HSTEP_VISITED {
+            return false;
+        }
+        if idim == counts.len() {
+            if half_steps == 0 {
+                self.found.push(counts.to_vec());
             }
+            return true;
         }
-        // search for the upscaling set
-        'limit: for maxval in 0.. {
-            // construct an iterator that goes 0, 1, -1, 2, -2, ...
-            let signed_iterator = (-maxval+1..1).rev().interleave(1..maxval);
-            for ithird in signed_iterator.clone() {
-                for ifourth in signed_iterator.clone() {
-                    for ififth in signed_iterator.clone() {
-                        // skip the empty set
-                        if ithird == 0 && ifourth == 0 && ififth == 0 {
-                            continue;
-                        }
-                        let total_half_steps = JustInterval::MajorThird.get_half_steps() * ithird
-                                             + JustInterval::PerfectFourth.get_half_steps() * ifourth
-                                             + JustInterval::PerfectFifth.get_half_steps() * ififth;
-                        let total_freq_scale = JustInterval::MajorThird.get_freq_scale().pow(ithird)
-                                             * JustInterval::PerfectFourth.get_freq_scale().pow(ifourth)
-                                             * JustInterval::PerfectFifth.get_freq_scale().pow(ififth);
-                        if total_half_steps == 0 &&
-                           total_freq_scale > 1 &&
-                           ithird != -interval_sets.0.n_major_third &&
-                           ifourth != -interval_sets.0.n_perfect_fourth &&
-                           ififth != -interval_sets.0.n_perfect_fifth {
-                            interval_sets.1.n_major_third = ithird;
-                            interval_sets.1.n_perfect_fourth = ifourth;
-                            interval_sets.1.n_perfect_fifth = ififth;
-                            break 'limit;
-                        }
-                    }
-                }
+        if half_steps.abs() > i64::from(max_intervals)*self.max_steps[idim] {
+            return true;
+        }
+        for count in -max_intervals..=max_intervals {
+            counts[idim] = count;
+            let remaining = half_steps - i64::from(count)*i64::from(self.basis[idim].get_half_steps());
+            if !self.collect(counts, idim+1, remaining, max_intervals - count.abs()) {
+                return false;
             }
         }
-        interval_sets
+        counts[idim] = 0;
+        true
+    }
+}
+
+fn half_steps_of(basis: &[Interval], counts: &[i32]) -> i32 {
+    basis.iter().zip(counts)
+        .fold(0, |acc, (interval, &count)| acc + interval.get_half_steps() * count)
+}
+
+fn monzo_of(basis: &[Interval], counts: &[i32]) -> Monzo {
+    basis.iter().zip(counts)
+        .fold(Monzo::unison(), |acc, (interval, &count)| &acc * &(&interval.get_monzo()).pow(count))
+}
+
+impl IntervalSet {
+    pub fn new_empty(basis: &[Interval]) -> Self {
+        IntervalSet::new_from_vals(basis, vec![0; basis.len()])
+    }
+
+    pub fn new_from_vals(basis: &[Interval], counts: Vec<i32>) -> Self {
+        assert_eq!(basis.len(), counts.len(), "Every basis interval needs a count");
+        Self {basis: basis.to_vec(), counts}
+    }
+
+    pub fn new_from_intervals<I>(basis: &[Interval], intervals: &[(I, i32)]) -> Result<Self, Error>
+        where I: Clone + Into<Interval> {
+        let mut interval_set = IntervalSet::new_empty(basis);
+        for (interval, count) in intervals {
+            let interval: Interval = interval.clone().into();
+            if interval == Interval::unison() {
+                continue;
+            }
+            let (idim, sign) = interval_set.find_dim(&interval)
+                .ok_or_else(|| Error::InvalidInterval {name: interval.get_name(), reason: String::from("not part of the basis")})?;
+            interval_set.counts[idim] += sign*count;
+        }
+        Ok(interval_set)
+    }
+
+    pub fn new_with_hstep(half_steps: i32, vocabulary: &[Interval]) -> Result<Self, Error> {
+        check_reachable(half_steps, vocabulary)?;
+        Ok(IntervalSet::new_from_vals(vocabulary, solve_half_steps(half_steps, vocabulary)))
+    }
+
+    // all interval sets with at most max_intervals intervals that span the half steps, shortest first
+    pub fn all_with_hstep(half_steps: i32, vocabulary: &[Interval], max_intervals: i32) -> Result<Vec<Self>, Error> {
+        let mut max_steps = vec![0; vocabulary.len()];
+        for idim in (0..vocabulary.len()).rev() {
+            let next_max = max_steps.get(idim+1).copied().unwrap_or(0);
+            max_steps[idim] = next_max.max(i64::from(vocabulary[idim].get_half_steps().abs()));
+        }
+        let mut search = HalfStepSearch {basis: vocabulary, max_steps, found: Vec::new(), visited: 0};
+        let mut counts = vec![0; vocabulary.len()];
+        if !search.collect(&mut counts, 0, i64::from(half_steps), max_intervals) {
+            return Err(Error::SearchLimitExceeded {visited: MAX_HSTEP_VISITED, half_steps, target: None});
+        }
+        let mut interval_sets: Vec<Self> = search.found.into_iter()
+            .map(|counts| IntervalSet::new_from_vals(vocabulary, counts))
+            .collect();
+        interval_sets.sort_by_key(|interval_set| interval_set.num_intervals());
+        Ok(interval_sets)
+    }
+
+    // index of the basis interval and the sign with which it represents the given interval
+    fn find_dim(&self, interval: &Interval) -> Option<(usize, i32)> {
+        self.basis.iter().enumerate().find_map(|(idim, basis)| {
+            if basis == interval {
+                Some((idim, 1))
+            } else if -basis.clone() == *interval {
+                Some((idim, -1))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn get_basis(&self) -> &[Interval] {
+        &self.basis
+    }
+
+    pub fn get_counts(&self) -> &[i32] {
+        &self.counts
     }
 
     pub fn get_half_steps(&self) -> i32 {
-        JustInterval::MajorThird.get_half_steps() * self.n_major_third +
-        JustInterval::PerfectFourth.get_half_steps() * self.n_perfect_fourth +
-        JustInterval::PerfectFifth.get_half_steps() * self.n_perfect_fifth
+        half_steps_of(&self.basis, &self.counts)
     }
 
     pub fn get_freq_scale(&self) -> Rational {
-        JustInterval::MajorThird.get_freq_scale().pow(self.n_major_third) *
-        JustInterval::PerfectFourth.get_freq_scale().pow(self.n_perfect_fourth) *
-        JustInterval::PerfectFifth.get_freq_scale().pow(self.n_perfect_fifth)
+        self.get_monzo().to_rational()
+    }
+
+    pub fn get_monzo(&self) -> Monzo {
+        monzo_of(&self.basis, &self.counts)
     }
 
     pub fn num_intervals(&self) -> usize {
-        (self.n_major_third.abs()+self.n_perfect_fourth.abs()+self.n_perfect_fifth.abs()) as usize
+        self.counts.iter().map(|count| count.unsigned_abs() as usize).sum()
     }
 
     pub fn add(&mut self, other: &Self) {
-        self.n_major_third += other.n_major_third;
-        self.n_perfect_fourth += other.n_perfect_fourth;
-        self.n_perfect_fifth += other.n_perfect_fifth;
+        assert_eq!(self.basis, other.basis, "Interval sets need to share their basis");
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
     }
 
     pub fn to_interval_sequence(&self) -> IntervalSequence {
-        let element_counts = vec![
-            (self.n_major_third.abs() as usize,    JustInterval::MajorThird*self.n_major_third),
-            (self.n_perfect_fourth.abs() as usize, JustInterval::PerfectFourth*self.n_perfect_fourth),
-            (self.n_perfect_fifth.abs() as usize,  JustInterval::PerfectFifth*self.n_perfect_fifth)];
+        self.to_interval_sequence_ordered(SequenceOrder::Even)
+    }
+
+    pub fn to_interval_sequence_ordered(&self, sequence_order: SequenceOrder) -> IntervalSequence {
+        let element_counts = self.basis.iter().zip(&self.counts)
+            .map(|(interval, &count)| (count.unsigned_abs() as usize, interval.clone()*count))
+            .collect();
+        IntervalSet::sequence_of(order(&element_counts, sequence_order, Interval::get_half_steps))
+    }
+
+    // Sequence whose notes from the starting note on stay within the ranges of the instruments,
+    // which play the notes in turn. The given order is kept where the ranges allow,
+    // intervals may be replaced by their octave complements, which leaves the frequency scaling unchanged.
+    pub fn to_interval_sequence_in_range(&self, startingnote: Note, ranges: &[NoteRange],
+                                         sequence_order: SequenceOrder) -> Result<IntervalSequence, Error> {
+        let intervals = self.to_interval_sequence_ordered(sequence_order).intervals;
+        Ok(IntervalSet::sequence_of(order_in_range(&intervals, &self.basis, startingnote, ranges)?))
+    }
+
+    fn sequence_of(intervals: Vec<Interval>) -> IntervalSequence {
         let mut interval_seq = IntervalSequence::new();
-        interval_seq.intervals = distribute(&element_counts);
+        interval_seq.intervals = intervals;
         interval_seq.half_steps = interval_seq.intervals.iter()
             .fold(0, |acc, interval| acc + interval.get_half_steps());
-        interval_seq.freq_scale = interval_seq.intervals.iter()
-            .fold(Rational::from((1,1)), |acc, interval| acc * interval.get_freq_scale());
+        interval_seq.monzo = interval_seq.intervals.iter()
+            .fold(Monzo::unison(), |acc, interval| &acc * &interval.get_monzo());
         interval_seq
     }
 }
 
+impl Neg for IntervalSet {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        self*(-1)
+    }
+}
+
+impl Mul<i32> for IntervalSet {
+    type Output = Self;
+    fn mul(mut self, rhs: i32) -> Self::Output {
+        for count in self.counts.iter_mut() {
+            *count *= rhs;
+        }
+        self
+    }
+}
+
+impl AddAssign<&IntervalSet> for IntervalSet {
+    fn add_assign(&mut self, rhs: &IntervalSet) {
+        self.add(rhs);
+    }
+}
+
+impl SubAssign<&IntervalSet> for IntervalSet {
+    fn sub_assign(&mut self, rhs: &IntervalSet) {
+        self.add(&(-rhs.clone()));
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::just_intervals::JustInterval;
+    use crate::interval_vocabulary::builtin_vocabulary;
+
+    fn third_fourth_fifth(n_major_third: i32, n_perfect_fourth: i32, n_perfect_fifth: i32) -> IntervalSet {
+        set_of(&[(JustInterval::MajorThird, n_major_third),
+                 (JustInterval::PerfectFourth, n_perfect_fourth),
+                 (JustInterval::PerfectFifth, n_perfect_fifth)])
+    }
+
+    fn set_of(intervals: &[(JustInterval, i32)]) -> IntervalSet {
+        IntervalSet::new_from_intervals(&builtin_vocabulary(13), intervals).unwrap()
+    }
 
     #[test]
     fn search_interval_sets_with_hstep() {
-        for hstep in -10..10 {
-            assert_eq!(IntervalSet::new_with_hstep(hstep).to_interval_sequence().get_half_steps(), hstep);
+        for prime_limit in [3, 5, 7, 11, 13] {
+            let vocabulary = builtin_vocabulary(prime_limit);
+            for hstep in -10..10 {
+                assert_eq!(IntervalSet::new_with_hstep(hstep, &vocabulary).unwrap().to_interval_sequence().get_half_steps(), hstep);
+            }
+            // every interval of the vocabulary is its own shortest solution
+            for interval in &vocabulary {
+                assert_eq!(IntervalSet::new_with_hstep(interval.get_half_steps(), &vocabulary).unwrap().num_intervals(), 1);
+            }
+            assert_eq!(IntervalSet::new_with_hstep(0, &vocabulary), Ok(IntervalSet::new_empty(&vocabulary)));
         }
+        // the search never leaves the vocabulary
+        assert_eq!(IntervalSet::new_with_hstep(10, &builtin_vocabulary(5)).unwrap().num_intervals(), 2);
+        let vocabulary = builtin_vocabulary(7);
+        assert_eq!(IntervalSet::new_with_hstep(10, &vocabulary),
+                   Ok(IntervalSet::new_from_intervals(&vocabulary, &[(JustInterval::HarmonicSeventh, 1)]).unwrap()));
+        // runtime vocabularies work the same way
+        let vocabulary = vec![Interval::new("Fifth", Rational::from((3,2)), 7),
+                              Interval::new("HarmonicSeventh", Rational::from((7,4)), 10)];
+        let interval_set = IntervalSet::new_with_hstep(-1, &vocabulary).unwrap();
+        assert_eq!(interval_set, IntervalSet::new_from_vals(&vocabulary, vec![-3, 2]));
+        assert_eq!(interval_set.get_freq_scale(), Rational::from((49,54)));
     }
+
     #[test]
-    fn search_interval_sets_with_freq_scale() {
-        let interval_sets = IntervalSet::new_with_freq_scale();
-        assert!(interval_sets.0.to_interval_sequence().get_half_steps() == 0);
-        assert!(interval_sets.0.to_interval_sequence().get_freq_scale() < 1);
-        assert!(interval_sets.1.to_interval_sequence().get_half_steps() == 0);
-        assert!(interval_sets.1.to_interval_sequence().get_freq_scale() > 1);
+    fn solve_hstep_exactly() {
+        // the exact solution is as short as the exhaustive enumeration
+        for prime_limit in [3, 5, 7] {
+            let vocabulary = builtin_vocabulary(prime_limit);
+            for hstep in -30..30 {
+                let interval_set = IntervalSet::new_with_hstep(hstep, &vocabulary).unwrap();
+                let shortest = &IntervalSet::all_with_hstep(hstep, &vocabulary, interval_set.num_intervals() as i32).unwrap()[0];
+                assert_eq!(interval_set.get_half_steps(), hstep);
+                assert_eq!(interval_set.num_intervals(), shortest.num_intervals());
+            }
+        }
+        // large distances are mostly covered by the largest interval
+        let vocabulary = builtin_vocabulary(5);
+        let interval_set = IntervalSet::new_with_hstep(1000, &vocabulary).unwrap();
+        assert_eq!(interval_set.get_half_steps(), 1000);
+        assert_eq!(interval_set.num_intervals(), 112);
+        for half_steps in [i32::MAX, i32::MIN] {
+            assert_eq!(IntervalSet::new_with_hstep(half_steps, &vocabulary).unwrap().get_half_steps(), half_steps);
+        }
+        let interval_set = IntervalSet::new_with_hstep(-100000, &builtin_vocabulary(13)).unwrap();
+        assert_eq!(interval_set.get_half_steps(), -100000);
+        assert_eq!(interval_set.num_intervals(), 10000);
+        // intervals without half steps are never used
+        let vocabulary = vec![Interval::new("SyntonicComma", Rational::from((81,80)), 0),
+                              Interval::new("Fifth", Rational::from((3,2)), 7),
+                              Interval::new("Fourth", Rational::from((4,3)), 5)];
+        assert_eq!(IntervalSet::new_with_hstep(2, &vocabulary),
+                   Ok(IntervalSet::new_from_vals(&vocabulary, vec![0, 1, -1])));
+        // unreachable half steps
+        let vocabulary = vec![Interval::new("WholeTone", Rational::from((9,8)), 2),
+                              Interval::new("MajorThird", Rational::from((5,4)), 4)];
+        assert_eq!(IntervalSet::new_with_hstep(3, &vocabulary),
+                   Err(Error::UnreachableHalfSteps {half_steps: 3, multiple: 2}));
+        assert_eq!(IntervalSet::new_with_hstep(-6, &vocabulary).unwrap().num_intervals(), 2);
+        let vocabulary = vec![Interval::new("SyntonicComma", Rational::from((81,80)), 0)];
+        assert!(IntervalSet::new_with_hstep(1, &vocabulary).is_err());
+        assert_eq!(IntervalSet::new_with_hstep(0, &vocabulary), Ok(IntervalSet::new_empty(&vocabulary)));
+        assert_eq!(IntervalSet::new_with_hstep(0, &[]), Ok(IntervalSet::new_empty(&[])));
+    }
+
+    #[test]
+    fn enumerate_hstep_solutions() {
+        let vocabulary = vec![Interval::new("Fifth", Rational::from((3,2)), 7),
+                              Interval::new("Fourth", Rational::from((4,3)), 5)];
+        let interval_sets = IntervalSet::all_with_hstep(2, &vocabulary, 4).unwrap();
+        assert_eq!(interval_sets, vec![IntervalSet::new_from_vals(&vocabulary, vec![1, -1])]);
+        let interval_sets = IntervalSet::all_with_hstep(2, &vocabulary, 10).unwrap();
+        assert_eq!(interval_sets, vec![IntervalSet::new_from_vals(&vocabulary, vec![1, -1]),
+                                       IntervalSet::new_from_vals(&vocabulary, vec![-4, 6])]);
+        assert!(IntervalSet::all_with_hstep(2, &vocabulary, 1).unwrap().is_empty());
+        // far half steps and large counts neither overflow nor enumerate forever
+        assert!(IntervalSet::all_with_hstep(i32::MAX, &vocabulary, 4).unwrap().is_empty());
+        let limit = Err(Error::SearchLimitExceeded {visited: MAX_HSTEP_VISITED, half_steps: 1, target: None});
+        assert_eq!(IntervalSet::all_with_hstep(1, &vocabulary, i32::MAX), limit);
+        assert_eq!(IntervalSet::all_with_hstep(1, &builtin_vocabulary(5), MAX_LISTED_INTERVALS), limit);
+        // the exact solution is always the first one, and all solutions are distinct
+        for prime_limit in [5, 7] {
+            let vocabulary = builtin_vocabulary(prime_limit);
+            for hstep in -12..12 {
+                let interval_sets = IntervalSet::all_with_hstep(hstep, &vocabulary, 3).unwrap();
+                let shortest = IntervalSet::new_with_hstep(hstep, &vocabulary).unwrap();
+                if shortest.num_intervals() <= 3 {
+                    assert_eq!(interval_sets[0].num_intervals(), shortest.num_intervals());
+                    assert!(interval_sets.contains(&shortest));
+                }
+                for (i, interval_set) in interval_sets.iter().enumerate() {
+                    assert_eq!(interval_set.get_half_steps(), hstep);
+                    assert!(interval_set.num_intervals() <= 3);
+                    assert!(!interval_sets[i+1..].contains(interval_set));
+                }
+            }
+        }
+        // no half steps are spanned by the empty set and the pythagorean unison (9/8 * 4/3 / 3/2) and its inverse
+        let vocabulary = builtin_vocabulary(3);
+        let interval_sets = IntervalSet::all_with_hstep(0, &vocabulary, 3).unwrap();
+        assert_eq!(interval_sets.len(), 3);
+        assert!(interval_sets.iter().all(|interval_set| interval_set.get_freq_scale() == 1));
+    }
+
+    #[test]
+    fn new_from_intervals() {
+        assert_eq!(set_of(&[(JustInterval::IMajorThird, 2)]),
+                   set_of(&[(JustInterval::MajorThird, -2)]));
+        assert_eq!(set_of(&[(JustInterval::MinorSixth, 1),
+                 (JustInterval::IMinorSixth, 1),
+                 (JustInterval::Unison, 3)]),
+                   IntervalSet::new_empty(&builtin_vocabulary(13)));
+        // the basis does not need to consist of ascending intervals
+        let basis = vec![Interval::from(JustInterval::IMajorThird), Interval::from(JustInterval::PerfectFifth)];
+        assert_eq!(IntervalSet::new_from_intervals(&basis, &[(JustInterval::MajorThird, 2),
+                                                             (JustInterval::IPerfectFifth, 1)]),
+                   Ok(IntervalSet::new_from_vals(&basis, vec![-2, -1])));
+        assert_eq!(IntervalSet::new_from_intervals(&basis, &[(JustInterval::MajorWholeTone, 1)]).unwrap_err().to_string(),
+                   "Interval 'MajorWholeTone': not part of the basis");
     }
 
     #[test]
     fn add_interval_sets() {
-        let mut set = IntervalSet::new_from_vals(1,2,3);
-        set.add(&IntervalSet::new_from_vals(1,2,3));
-        let ref_set = IntervalSet::new_from_vals(2,4,6);
+        let mut set = third_fourth_fifth(1,2,3);
+        set.add(&third_fourth_fifth(1,2,3));
+        let ref_set = third_fourth_fifth(2,4,6);
         assert_eq!(set, ref_set);
 
-        let mut set = IntervalSet::new_from_vals(1,2,3);
-        set.add(&IntervalSet::new_from_vals(-1,-2,-3));
-        let ref_set = IntervalSet::new_from_vals(0,0,0);
+        let mut set = third_fourth_fifth(1,2,3);
+        set.add(&third_fourth_fifth(-1,-2,-3));
+        let ref_set = third_fourth_fifth(0,0,0);
         assert_eq!(set, ref_set);
 
-        let mut set = IntervalSet::new_from_vals(1,2,3);
-        set.add(&IntervalSet::new_from_vals(-2,-4,-6));
-        let ref_set = IntervalSet::new_from_vals(-1,-2,-3);
+        let mut set = third_fourth_fifth(1,2,3);
+        set.add(&third_fourth_fifth(-2,-4,-6));
+        let ref_set = third_fourth_fifth(-1,-2,-3);
         assert_eq!(set, ref_set);
     }
 
+    #[test]
+    #[should_panic]
+    fn add_interval_sets_with_different_basis() {
+        let mut set = IntervalSet::new_empty(&builtin_vocabulary(5));
+        set.add(&IntervalSet::new_empty(&builtin_vocabulary(7)));
+    }
+
+    #[test]
+    fn vector_operations() {
+        let set = third_fourth_fifth(1,2,3);
+        let other = third_fourth_fifth(-2,0,1);
+        let mut sum = set.clone();
+        sum += &other;
+        assert_eq!(sum, third_fourth_fifth(-1,2,4));
+        let mut difference = set.clone();
+        difference -= &other;
+        assert_eq!(difference, third_fourth_fifth(3,2,2));
+        assert_eq!(-set.clone(), third_fourth_fifth(-1,-2,-3));
+        assert_eq!(set.clone()*3, third_fourth_fifth(3,6,9));
+        assert_eq!(set.clone()*(-2), third_fourth_fifth(-2,-4,-6));
+        // half steps add up and frequency scalings multiply
+        assert_eq!(sum.get_half_steps(), set.get_half_steps() + other.get_half_steps());
+        assert_eq!(sum.get_freq_scale(), set.get_freq_scale() * other.get_freq_scale());
+        assert_eq!(sum.get_monzo(), &set.get_monzo() * &other.get_monzo());
+        assert_eq!((-set.clone()).get_freq_scale(), Rational::from(1) / set.get_freq_scale());
+    }
+
+    #[test]
+    fn arbitrary_dimensions() {
+        let interval_set = IntervalSet::new_empty(&[]);
+        assert_eq!(interval_set.get_half_steps(), 0);
+        assert_eq!(interval_set.get_freq_scale(), 1);
+        assert_eq!(interval_set.num_intervals(), 0);
+        assert_eq!(interval_set.to_interval_sequence(), IntervalSequence::new());
+
+        let fifths = vec![Interval::from(JustInterval::PerfectFifth)];
+        let mut interval_set = IntervalSet::new_from_vals(&fifths, vec![-3]);
+        interval_set.add(&IntervalSet::new_from_vals(&fifths, vec![1]));
+        assert_eq!(interval_set.get_counts(), &[-2]);
+        assert_eq!(interval_set.get_half_steps(), -14);
+        assert_eq!(interval_set.get_freq_scale(), Rational::from((4,9)));
+        assert_eq!(interval_set.num_intervals(), 2);
+        assert_eq!(interval_set.to_interval_sequence().intervals,
+                   vec![Interval::from(JustInterval::IPerfectFifth); 2]);
+
+        let vocabulary = builtin_vocabulary(13);
+        let interval_set = IntervalSet::new_from_vals(&vocabulary, vec![1; vocabulary.len()]);
+        assert_eq!(interval_set.get_basis(), vocabulary.as_slice());
+        assert_eq!(interval_set.get_half_steps(),
+                   vocabulary.iter().map(|interval| interval.get_half_steps()).sum::<i32>());
+        assert_eq!(interval_set.get_freq_scale(),
+                   vocabulary.iter().map(|interval| interval.get_freq_scale()).product::<Rational>());
+        assert_eq!(interval_set.num_intervals(), vocabulary.len());
+        let sequence = interval_set.to_interval_sequence();
+        assert_eq!(sequence.get_half_steps(), interval_set.get_half_steps());
+        assert_eq!(sequence.get_freq_scale(), interval_set.get_freq_scale());
+        for interval in &vocabulary {
+            assert!(sequence.intervals.contains(interval));
+        }
+    }
+
     #[test]
     fn get_half_steps() {
-        assert_eq!(IntervalSet::new_from_vals(1,2,2).get_half_steps(), 28);
+        assert_eq!(third_fourth_fifth(1,2,2).get_half_steps(), 28);
+        assert_eq!(set_of(&[(JustInterval::MinorThird, 1),
+                 (JustInterval::IMajorSixth, 2)]).get_half_steps(), -15);
     }
 
     #[test]
     fn get_freq_scale() {
-        assert_eq!(IntervalSet::new_from_vals(1,2,-2).get_freq_scale(), Rational::from((80,81)));
+        assert_eq!(third_fourth_fifth(1,2,-2).get_freq_scale(), Rational::from((80,81)));
+        assert_eq!(set_of(&[(JustInterval::DiatonicSemitone, 2),
+                 (JustInterval::IMinorWholeTone, 1)]).get_freq_scale(),
+                   Rational::from((128,125)));
+        // septimal comma
+        let interval_set = set_of(&[(JustInterval::SeptimalWholeTone, 1),
+                         (JustInterval::IMajorWholeTone, 1)]);
+        assert_eq!(interval_set.get_half_steps(), 0);
+        assert_eq!(interval_set.get_freq_scale(), Rational::from((64,63)));
+    }
+
+    #[test]
+    fn num_intervals() {
+        assert_eq!(third_fourth_fifth(1,-2,2).num_intervals(), 5);
+        assert_eq!(set_of(&[(JustInterval::MajorSixth, -3),
+                 (JustInterval::MinorWholeTone, 1)]).num_intervals(), 4);
     }
 
     #[test]
     fn to_interval_sequence() {
-        let interval_set = IntervalSet::new_from_vals(2, 3, -2);
+        let interval_set = third_fourth_fifth(2, 3, -2);
         let mut ref_sequence = IntervalSequence::new();
         ref_sequence.add_interval(JustInterval::PerfectFourth);
         ref_sequence.add_interval(JustInterval::MajorThird);
@@ -208,7 +562,7 @@ mod test {
         ref_sequence.add_interval(JustInterval::PerfectFourth);
         assert_eq!(interval_set.to_interval_sequence(), ref_sequence);
 
-        let interval_set = IntervalSet::new_from_vals(-2, -3, 2);
+        let interval_set = third_fourth_fifth(-2, -3, 2);
         let mut ref_sequence = IntervalSequence::new();
         ref_sequence.add_interval(JustInterval::IPerfectFourth);
         ref_sequence.add_interval(JustInterval::IMajorThird);
@@ -218,5 +572,66 @@ mod test {
         ref_sequence.add_interval(JustInterval::PerfectFifth);
         ref_sequence.add_interval(JustInterval::IPerfectFourth);
         assert_eq!(interval_set.to_interval_sequence(), ref_sequence);
+
+        let interval_set = set_of(&[(JustInterval::MajorWholeTone, 1),
+                         (JustInterval::IMinorWholeTone, 1)]);
+        let sequence = interval_set.to_interval_sequence();
+        assert_eq!(sequence.intervals, vec![Interval::from(JustInterval::IMinorWholeTone),
+                                            Interval::from(JustInterval::MajorWholeTone)]);
+        assert_eq!(sequence.get_freq_scale(), Rational::from((81,80)));
+    }
+
+    #[test]
+    fn to_interval_sequence_in_range() {
+        let interval_set = third_fourth_fifth(3, 0, -2);
+        // the evenly distributed thirds up and fifths down reach F#3
+        let range: NoteRange = "A3..F4".parse().unwrap();
+        assert!(interval_set.to_interval_sequence().to_notes(Note::new("C", 4)).unwrap().iter().any(|note| !range.contains(note)));
+        let sequence = interval_set.to_interval_sequence_in_range(Note::new("C", 4), &[range], SequenceOrder::Even).unwrap();
+        assert!(sequence.to_notes(Note::new("C", 4)).unwrap().iter().all(|note| range.contains(note)));
+        assert_eq!(sequence.get_half_steps(), interval_set.get_half_steps());
+        assert_eq!(sequence.get_freq_scale(), interval_set.get_freq_scale());
+        assert!(interval_set.to_interval_sequence_in_range(Note::new("C", 4), &["C4..D4".parse().unwrap()], SequenceOrder::Even).is_err());
+    }
+
+    #[test]
+    fn ordered_sequences() {
+        use JustInterval::*;
+        let interval_set = third_fourth_fifth(2, 3, -2);
+        let names = |sequence_order| interval_set.to_interval_sequence_ordered(sequence_order).intervals.iter()
+            .map(Interval::get_name)
+            .collect::<Vec<_>>();
+        // every order plays the same intervals
+        let mut even = names(SequenceOrder::Even);
+        even.sort();
+        for sequence_order in [SequenceOrder::Euclidean, SequenceOrder::Random(3), SequenceOrder::Balanced, SequenceOrder::Grouped] {
+            let sequence = interval_set.to_interval_sequence_ordered(sequence_order);
+            assert_eq!(sequence.get_half_steps(), interval_set.get_half_steps());
+            assert_eq!(sequence.get_freq_scale(), interval_set.get_freq_scale());
+            let mut sorted = names(sequence_order);
+            sorted.sort();
+            assert_eq!(sorted, even);
+        }
+        assert_eq!(interval_set.to_interval_sequence_ordered(SequenceOrder::Even), interval_set.to_interval_sequence());
+        assert_eq!(names(SequenceOrder::Euclidean),
+                   vec!["IPerfectFifth", "PerfectFourth", "MajorThird", "PerfectFourth", "IPerfectFifth", "PerfectFourth", "MajorThird"]);
+        assert_eq!(names(SequenceOrder::Grouped),
+                   vec!["PerfectFourth", "MajorThird", "PerfectFourth", "MajorThird", "PerfectFourth", "IPerfectFifth", "IPerfectFifth"]);
+        assert_eq!(names(SequenceOrder::Random(3)), names(SequenceOrder::Random(3)));
+        // the balanced walk strays less far from the starting note
+        let excursion = |sequence_order| interval_set.to_interval_sequence_ordered(sequence_order).intervals.iter()
+            .scan(0, |position, interval| {
+                *position += interval.get_half_steps();
+                Some(position.abs())
+            })
+            .max()
+            .unwrap();
+        assert_eq!(excursion(SequenceOrder::Balanced), 9);
+        assert_eq!(excursion(SequenceOrder::Even), 11);
+        assert_eq!(excursion(SequenceOrder::Grouped), 23);
+        // the ranges keep as much of the order as fits
+        let set = set_of(&[(MajorThird, 3), (IPerfectFifth, 2)]);
+        let sequence = set.to_interval_sequence_in_range(Note::new("C", 4), &["Bb3..Ab4".parse().unwrap()], SequenceOrder::Grouped).unwrap();
+        assert_eq!(sequence.intervals, [MajorThird, MajorThird, IPerfectFifth, MajorThird, IPerfectFifth].map(Interval::from));
     }
 }
@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use rug::Rational;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use crate::error::Error;
+use crate::intervals::Interval;
+use crate::interval_set::IntervalSet;
+use crate::commas::comma_basis;
+use crate::just_intervals::JustInterval;
+use crate::scala::parse_scl;
+
+// Layout of a vocabulary file, e.g. in TOML:
+//    [[interval]]
+//    name = "HarmonicSeventh"
+//    ratio = "7/4"
+//    half_steps = 10
+// half_steps may be omitted, it then defaults to the nearest 12-TET value.
+// Only Scala files can be read without the serde feature.
+#[cfg(feature = "serde")]
+#[derive(Debug, Deserialize)]
+struct VocabularyFile {
+    interval: Vec<IntervalEntry>
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+struct IntervalEntry {
+    name: String,
+    ratio: String,
+    half_steps: Option<i32>
+}
+
+pub fn builtin_vocabulary(prime_limit: u32) -> Vec<Interval> {
+    JustInterval::vocabulary(prime_limit).into_iter()
+        .map(Interval::from)
+        .collect()
+}
+
+pub fn load_vocabulary(path: &Path) -> Result<Vec<Interval>, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| Error::InvalidVocabulary(format!("Cannot read interval file {}: {}", path.display(), err)))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => vocabulary_from_toml(&content),
+        Some("json") => vocabulary_from_json(&content),
+        Some("scl") => vocabulary_from_scl(&content),
+        _ => Err(Error::InvalidVocabulary(format!("Unknown interval file format {} (expected .toml, .json or .scl)", path.display())))
+    }
+}
+
+#[cfg(feature = "serde")]
+pub fn vocabulary_from_toml(content: &str) -> Result<Vec<Interval>, Error> {
+    let file: VocabularyFile = toml::from_str(content)
+        .map_err(|err| Error::InvalidVocabulary(format!("Invalid TOML interval file: {}", err)))?;
+    parse_entries(&file.interval)
+}
+
+#[cfg(feature = "serde")]
+pub fn vocabulary_from_json(content: &str) -> Result<Vec<Interval>, Error> {
+    let file: VocabularyFile = serde_json::from_str(content)
+        .map_err(|err| Error::InvalidVocabulary(format!("Invalid JSON interval file: {}", err)))?;
+    parse_entries(&file.interval)
+}
+
+#[cfg(not(feature = "serde"))]
+pub fn vocabulary_from_toml(_content: &str) -> Result<Vec<Interval>, Error> {
+    Err(Error::InvalidVocabulary(String::from("TOML interval files need the serde feature")))
+}
+
+#[cfg(not(feature = "serde"))]
+pub fn vocabulary_from_json(_content: &str) -> Result<Vec<Interval>, Error> {
+    Err(Error::InvalidVocabulary(String::from("JSON interval files need the serde feature")))
+}
+
+// The degrees of a Scala scale between 1/1 and the closing 2/1 become intervals named after their ratios,
+// the octave is left out like in the built-in vocabularies
+pub fn vocabulary_from_scl(content: &str) -> Result<Vec<Interval>, Error> {
+    let entries: Vec<IntervalEntry> = parse_scl(content)?.into_iter()
+        .filter(|degree| *degree != 1 && *degree != 2)
+        .map(|degree| {
+            let ratio = format!("{}/{}", degree.numer(), degree.denom());
+            IntervalEntry {name: ratio.clone(), ratio, half_steps: None}
+        })
+        .collect();
+    parse_entries(&entries)
+}
+
+fn parse_entries(entries: &[IntervalEntry]) -> Result<Vec<Interval>, Error> {
+    let mut names = HashSet::new();
+    let mut vocabulary = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let invalid = |reason: String| Error::InvalidInterval {name: entry.name.clone(), reason};
+        if !names.insert(entry.name.as_str()) {
+            return Err(Error::InvalidVocabulary(format!("Duplicated interval name '{}'", entry.name)));
+        }
+        let freq_scale = Rational::parse(entry.ratio.trim())
+            .map(Rational::from)
+            .map_err(|_| invalid(format!("'{}' is not a valid ratio", entry.ratio)))?;
+        if freq_scale <= 0 {
+            return Err(invalid(format!("ratio {} is not positive", freq_scale)));
+        }
+        if freq_scale == 1 {
+            return Err(invalid(String::from("ratio 1 is a unison")));
+        }
+        let nearest_half_steps = Interval::nearest_half_steps(&freq_scale);
+        let half_steps = entry.half_steps.unwrap_or(nearest_half_steps);
+        if half_steps != nearest_half_steps {
+            return Err(invalid(format!("{} half steps do not match the nearest 12-TET value {} of ratio {}",
+                                       half_steps, nearest_half_steps, freq_scale)));
+        }
+        vocabulary.push(Interval::try_new(&entry.name, freq_scale, half_steps)?);
+    }
+    check_vocabulary(&vocabulary)?;
+    Ok(vocabulary)
+}
+
+// The interval set searches require that every number of half steps can be reached
+// and that at least one combination of intervals without half steps changes the frequency.
+pub fn check_vocabulary(vocabulary: &[Interval]) -> Result<(), Error> {
+    if vocabulary.is_empty() {
+        return Err(Error::InvalidVocabulary(String::from("The interval vocabulary is empty")));
+    }
+    // a single half step is reachable iff every number of half steps is
+    IntervalSet::new_with_hstep(1, vocabulary)?;
+    if comma_basis(vocabulary).is_empty() {
+        return Err(Error::InvalidVocabulary(String::from("The interval vocabulary cannot form any comma")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin() {
+        let vocabulary = builtin_vocabulary(5);
+        assert_eq!(vocabulary.len(), JustInterval::vocabulary(5).len());
+        assert!(vocabulary.contains(&Interval::from(JustInterval::MajorSixth)));
+        for prime_limit in [3, 5, 7, 11, 13] {
+            assert_eq!(check_vocabulary(&builtin_vocabulary(prime_limit)), Ok(()));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn from_toml() {
+        let vocabulary = vocabulary_from_toml(r#"
+            [[interval]]
+            name = "HarmonicSeventh"
+            ratio = "7/4"
+            half_steps = 10
+
+            [[interval]]
+            name = "Fifth"
+            ratio = "3/2"
+            "#).unwrap();
+        assert_eq!(vocabulary, vec![Interval::new("HarmonicSeventh", Rational::from((7,4)), 10),
+                                    Interval::new("Fifth", Rational::from((3,2)), 7)]);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn from_json() {
+        let vocabulary = vocabulary_from_json(r#"
+            {"interval": [{"name": "SeptimalMinorThird", "ratio": "7/6", "half_steps": 3},
+                          {"name": "MajorThird", "ratio": "5/4"}]}
+            "#).unwrap();
+        assert_eq!(vocabulary, vec![Interval::new("SeptimalMinorThird", Rational::from((7,6)), 3),
+                                    Interval::new("MajorThird", Rational::from((5,4)), 4)]);
+    }
+
+    #[test]
+    fn from_scl() {
+        let vocabulary = vocabulary_from_scl("! ji.scl\n!\nJust major triad\n 4\n!\n 1/1\n 5/4 third\n 3/2\n 2\n").unwrap();
+        assert_eq!(vocabulary, vec![Interval::new("5/4", Rational::from((5,4)), 4),
+                                    Interval::new("3/2", Rational::from((3,2)), 7)]);
+        assert_eq!(vocabulary_from_scl("fifths\n 2\n 3/2\n 6/4\n").unwrap_err().to_string(),
+                   "Duplicated interval name '3/2'");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn invalid_entries() {
+        let vocabulary = |entries: &str| vocabulary_from_toml(entries).unwrap_err().to_string();
+        assert_eq!(vocabulary("[[interval]]\nname = \"a\"\nratio = \"-3/2\"\n"),
+                   "Interval 'a': ratio -3/2 is not positive");
+        assert_eq!(vocabulary("[[interval]]\nname = \"a\"\nratio = \"0\"\n"),
+                   "Interval 'a': ratio 0 is not positive");
+        assert_eq!(vocabulary("[[interval]]\nname = \"a\"\nratio = \"three/2\"\n"),
+                   "Interval 'a': 'three/2' is not a valid ratio");
+        assert_eq!(vocabulary("[[interval]]\nname = \"a\"\nratio = \"2/2\"\n"),
+                   "Interval 'a': ratio 1 is a unison");
+        assert_eq!(vocabulary("[[interval]]\nname = \"a\"\nratio = \"3/2\"\nhalf_steps = 6\n"),
+                   "Interval 'a': 6 half steps do not match the nearest 12-TET value 7 of ratio 3/2");
+        assert_eq!(vocabulary("[[interval]]\nname = \"a\"\nratio = \"3/2\"\n\
+                               [[interval]]\nname = \"a\"\nratio = \"5/4\"\n"),
+                   "Duplicated interval name 'a'");
+        assert_eq!(vocabulary("[[interval]]\nname = \"a\"\nratio = \"65537/65536\"\n"),
+                   "Interval 'a': Ratio 65537/65536 contains prime factors larger than 65521");
+        assert!(vocabulary("[[interval]]\nratio = \"3/2\"\n").starts_with("Invalid TOML interval file"));
+        assert!(vocabulary_from_json("{").unwrap_err().to_string().starts_with("Invalid JSON interval file"));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn unusable_vocabulary() {
+        assert_eq!(vocabulary_from_toml("interval = []").unwrap_err(),
+                   Error::InvalidVocabulary(String::from("The interval vocabulary is empty")));
+        assert_eq!(vocabulary_from_toml("[[interval]]\nname = \"a\"\nratio = \"9/8\"\n\
+                                         [[interval]]\nname = \"b\"\nratio = \"10/9\"\n").unwrap_err(),
+                   Error::UnreachableHalfSteps {half_steps: 1, multiple: 2});
+        // a semitone and two semitones never form a comma without a third interval
+        assert_eq!(vocabulary_from_toml("[[interval]]\nname = \"a\"\nratio = \"3/2\"\n\
+                                         [[interval]]\nname = \"b\"\nratio = \"16/15\"\n\
+                                         [[interval]]\nname = \"c\"\nratio = \"256/225\"\n").map(|_| ()),
+                   Ok(()));
+        assert_eq!(vocabulary_from_toml("[[interval]]\nname = \"a\"\nratio = \"16/15\"\n\
+                                         [[interval]]\nname = \"b\"\nratio = \"256/225\"\n").unwrap_err().to_string(),
+                   "The interval vocabulary cannot form any comma");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn load_from_file() {
+        let path = std::env::temp_dir().join("just_out_of_tune_vocabulary.toml");
+        fs::write(&path, "[[interval]]\nname = \"Fifth\"\nratio = \"3/2\"\n\
+                          [[interval]]\nname = \"Third\"\nratio = \"5/4\"\n").unwrap();
+        assert_eq!(load_vocabulary(&path).unwrap().len(), 2);
+        fs::remove_file(&path).unwrap();
+
+        assert!(load_vocabulary(Path::new("intervals.txt")).unwrap_err().to_string().starts_with("Cannot read"));
+    }
+}
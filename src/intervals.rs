@@ -0,0 +1,213 @@
+use num_traits::Inv;
+use std::ops::{Neg,Mul};
+use std::cmp::Ordering;
+use rug::Rational;
+use crate::error::Error;
+use crate::just_intervals::JustInterval;
+use crate::monzo::Monzo;
+
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct Interval {
+    name: String,
+    freq_scale: Rational,
+    monzo: Monzo,
+    half_steps: i32,
+    inverted: bool
+}
+
+impl Interval {
+    // panics on ratios without monzo, use try_new for intervals from user input
+    pub(crate) fn new(name: &str, freq_scale: Rational, half_steps: i32) -> Self {
+        Interval::try_new(name, freq_scale, half_steps).unwrap()
+    }
+
+    pub fn try_new(name: &str, freq_scale: Rational, half_steps: i32) -> Result<Self, Error> {
+        let monzo = Monzo::try_from(&freq_scale)
+            .map_err(|err| Error::InvalidInterval {name: String::from(name), reason: err.to_string()})?;
+        Ok(Interval {name: String::from(name), freq_scale, monzo, half_steps, inverted: false})
+    }
+
+    pub fn unison() -> Self {
+        Interval::new("Unison", Rational::from((1,1)), 0)
+    }
+
+    // the octave is no part of the vocabularies, as it does not change the drift
+    pub fn octave() -> Self {
+        Interval::new("Octave", Rational::from(2), 12)
+    }
+
+    pub fn get_name(&self) -> String {
+        if self.inverted {
+            format!("I{}", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    pub fn get_freq_scale(&self) -> Rational {
+        self.freq_scale.clone()
+    }
+
+    pub fn get_monzo(&self) -> Monzo {
+        self.monzo.clone()
+    }
+
+    pub fn get_half_steps(&self) -> i32 {
+        self.half_steps
+    }
+
+    // number of letters the interval moves a note name by
+    pub fn get_letter_steps(&self) -> i32 {
+        self.monzo.get_letter_steps()
+    }
+
+    // Interval moved by an octave against its direction, e.g. a fourth down for a fifth up.
+    // It reaches the same pitch class with the same drift, an interval without half steps has none.
+    pub fn octave_complement(&self) -> Option<Interval> {
+        let octave = Interval::octave();
+        let (freq_scale, half_steps, sign) = match self.half_steps.cmp(&0) {
+            Ordering::Greater => (self.get_freq_scale()/octave.get_freq_scale(), self.half_steps - octave.half_steps, "-"),
+            Ordering::Less => (self.get_freq_scale()*octave.get_freq_scale(), self.half_steps + octave.half_steps, "+"),
+            Ordering::Equal => return None
+        };
+        (freq_scale != 1).then(|| Interval::new(&format!("{}{}8ve", self.get_name(), sign), freq_scale, half_steps))
+    }
+
+    // number of 12-TET half steps closest to the frequency ratio
+    pub fn nearest_half_steps(freq_scale: &Rational) -> i32 {
+        (12.0*freq_scale.to_f64().log2()).round() as i32
+    }
+}
+
+impl From<JustInterval> for Interval {
+    fn from(interval: JustInterval) -> Self {
+        if JustInterval::BASIS.contains(&-interval) {
+            // inverted intervals are named after their ascending counterpart
+            -Interval::from(-interval)
+        } else {
+            Interval::new(&format!("{:?}", interval), interval.get_freq_scale(), interval.get_half_steps())
+        }
+    }
+}
+
+impl Neg for Interval {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        if self.half_steps == 0 && self.freq_scale == 1 {
+            return self;
+        }
+        Interval {
+            name: self.name,
+            freq_scale: self.freq_scale.inv(),
+            monzo: self.monzo.inv(),
+            half_steps: -self.half_steps,
+            inverted: !self.inverted
+        }
+    }
+}
+
+impl Mul<i32> for Interval {
+    type Output = Self;
+    fn mul(self, rhs: i32) -> Self::Output {
+        match rhs.cmp(&0) {
+            Ordering::Greater => self,
+            Ordering::Equal   => Interval::unison(),
+            Ordering::Less    => -self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_just_interval() {
+        let interval = Interval::from(JustInterval::HarmonicSeventh);
+        assert_eq!(interval.get_name(), "HarmonicSeventh");
+        assert_eq!(interval.get_freq_scale(), Rational::from((7,4)));
+        assert_eq!(interval.get_half_steps(), 10);
+        assert_eq!(interval.get_monzo(), Monzo::new(vec![-2, 0, 0, 1]));
+
+        let interval = Interval::from(JustInterval::IMinorThird);
+        assert_eq!(interval.get_name(), "IMinorThird");
+        assert_eq!(interval.get_freq_scale(), Rational::from((5,6)));
+        assert_eq!(interval.get_half_steps(), -3);
+        assert_eq!(interval, -Interval::from(JustInterval::MinorThird));
+
+        assert_eq!(Interval::from(JustInterval::Unison), Interval::unison());
+    }
+
+    #[test]
+    fn negate() {
+        for value in JustInterval::BASIS {
+            let interval = Interval::from(value);
+            let negated = -interval.clone();
+            assert_eq!(interval, -negated.clone());
+            assert_eq!(negated, Interval::from(-value));
+            assert_eq!(interval.get_half_steps(), -(negated.get_half_steps()));
+            assert_eq!(interval.get_freq_scale(), (negated.get_freq_scale().inv()));
+            assert_eq!(interval.get_monzo(), (negated.get_monzo().inv()));
+        }
+        assert_eq!(-Interval::unison(), Interval::unison());
+    }
+
+    #[test]
+    fn invalid_ratio() {
+        assert_eq!(Interval::try_new("Negative", Rational::from(-2), 12).unwrap_err().to_string(),
+                   format!("Interval 'Negative': {}", Monzo::try_from(&Rational::from(-2)).unwrap_err()));
+    }
+
+    #[test]
+    fn multiply() {
+        let interval = Interval::new("Septimal", Rational::from((7,6)), 3);
+        for factor in -2..3 {
+            let reference = match factor.cmp(&0) {
+                Ordering::Greater => interval.clone(),
+                Ordering::Equal   => Interval::unison(),
+                Ordering::Less    => -interval.clone()
+            };
+            assert_eq!(interval.clone()*factor, reference);
+        }
+    }
+
+    #[test]
+    fn letter_steps() {
+        let letter_steps = [1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 3, 3, 4, 4, 5, 5, 5, 6];
+        for (value, steps) in JustInterval::BASIS.into_iter().zip(letter_steps) {
+            assert_eq!(Interval::from(value).get_letter_steps(), steps);
+            assert_eq!(Interval::from(-value).get_letter_steps(), -steps);
+        }
+    }
+
+    #[test]
+    fn octave_complement() {
+        let complement = Interval::from(JustInterval::PerfectFifth).octave_complement().unwrap();
+        assert_eq!(complement.get_name(), "PerfectFifth-8ve");
+        assert_eq!(complement.get_freq_scale(), Rational::from((3,4)));
+        assert_eq!(complement.get_half_steps(), -5);
+        assert_eq!(complement.get_monzo(), Interval::from(JustInterval::IPerfectFourth).get_monzo());
+        let complement = Interval::from(JustInterval::IHarmonicSeventh).octave_complement().unwrap();
+        assert_eq!(complement.get_name(), "IHarmonicSeventh+8ve");
+        assert_eq!((complement.get_freq_scale(), complement.get_half_steps()), (Rational::from((8,7)), 2));
+        // the complement keeps the letter of the note
+        for value in JustInterval::BASIS {
+            let complement = Interval::from(value).octave_complement().unwrap();
+            assert_eq!(complement.get_letter_steps(), Interval::from(value).get_letter_steps() - 7);
+        }
+        assert_eq!(Interval::new("Comma", Rational::from((81,80)), 0).octave_complement(), None);
+        assert_eq!(Interval::octave().octave_complement(), None);
+    }
+
+    #[test]
+    fn nearest_half_steps() {
+        assert_eq!(Interval::nearest_half_steps(&Rational::from((3,2))),   7);
+        assert_eq!(Interval::nearest_half_steps(&Rational::from((2,3))),  -7);
+        assert_eq!(Interval::nearest_half_steps(&Rational::from((7,4))),  10);
+        assert_eq!(Interval::nearest_half_steps(&Rational::from((11,8))),  6);
+        assert_eq!(Interval::nearest_half_steps(&Rational::from((81,80))), 0);
+        for value in JustInterval::BASIS {
+            assert_eq!(Interval::nearest_half_steps(&value.get_freq_scale()), value.get_half_steps());
+        }
+    }
+}
@@ -2,54 +2,214 @@ use num_traits::{Inv, };
 use std::ops::{Neg,Mul};
 use std::cmp::{Ordering};
 use rug::Rational;
+use crate::monzo::Monzo;
 
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
 pub enum JustInterval {
     Unison,
+    DiatonicSemitone,
+    IDiatonicSemitone,
+    MinorWholeTone,
+    IMinorWholeTone,
+    MajorWholeTone,
+    IMajorWholeTone,
+    MinorThird,
+    IMinorThird,
     MajorThird,
     IMajorThird,
     PerfectFourth,
     IPerfectFourth,
     PerfectFifth,
     IPerfectFifth,
+    MinorSixth,
+    IMinorSixth,
+    MajorSixth,
+    IMajorSixth,
+    SeptimalWholeTone,
+    ISeptimalWholeTone,
+    SeptimalMinorThird,
+    ISeptimalMinorThird,
+    SeptimalMajorThird,
+    ISeptimalMajorThird,
+    SeptimalTritone,
+    ISeptimalTritone,
+    HarmonicSeventh,
+    IHarmonicSeventh,
+    UndecimalNeutralThird,
+    IUndecimalNeutralThird,
+    UndecimalSuperFourth,
+    IUndecimalSuperFourth,
+    TridecimalSemitone,
+    ITridecimalSemitone,
+    TridecimalNeutralSixth,
+    ITridecimalNeutralSixth,
 }
 
 impl JustInterval {
+    // all ascending intervals, ordered by size
+    pub const BASIS: [JustInterval; 18] = [
+        JustInterval::DiatonicSemitone,
+        JustInterval::TridecimalSemitone,
+        JustInterval::MinorWholeTone,
+        JustInterval::MajorWholeTone,
+        JustInterval::SeptimalWholeTone,
+        JustInterval::SeptimalMinorThird,
+        JustInterval::MinorThird,
+        JustInterval::UndecimalNeutralThird,
+        JustInterval::MajorThird,
+        JustInterval::SeptimalMajorThird,
+        JustInterval::PerfectFourth,
+        JustInterval::UndecimalSuperFourth,
+        JustInterval::SeptimalTritone,
+        JustInterval::PerfectFifth,
+        JustInterval::MinorSixth,
+        JustInterval::TridecimalNeutralSixth,
+        JustInterval::MajorSixth,
+        JustInterval::HarmonicSeventh,
+    ];
+
     pub fn get_freq_scale(&self) -> Rational {
         match self {
             JustInterval::Unison => Rational::from((1,1)),
+            JustInterval::DiatonicSemitone => Rational::from((16,15)),
+            JustInterval::IDiatonicSemitone => JustInterval::DiatonicSemitone.get_freq_scale().inv(),
+            JustInterval::MinorWholeTone => Rational::from((10,9)),
+            JustInterval::IMinorWholeTone => JustInterval::MinorWholeTone.get_freq_scale().inv(),
+            JustInterval::MajorWholeTone => Rational::from((9,8)),
+            JustInterval::IMajorWholeTone => JustInterval::MajorWholeTone.get_freq_scale().inv(),
+            JustInterval::MinorThird => Rational::from((6,5)),
+            JustInterval::IMinorThird => JustInterval::MinorThird.get_freq_scale().inv(),
             JustInterval::MajorThird => Rational::from((5,4)),
             JustInterval::IMajorThird => JustInterval::MajorThird.get_freq_scale().inv(),
             JustInterval::PerfectFourth => Rational::from((4,3)),
             JustInterval::IPerfectFourth => JustInterval::PerfectFourth.get_freq_scale().inv(),
             JustInterval::PerfectFifth => Rational::from((3,2)),
-            JustInterval::IPerfectFifth => JustInterval::PerfectFifth.get_freq_scale().inv()
+            JustInterval::IPerfectFifth => JustInterval::PerfectFifth.get_freq_scale().inv(),
+            JustInterval::MinorSixth => Rational::from((8,5)),
+            JustInterval::IMinorSixth => JustInterval::MinorSixth.get_freq_scale().inv(),
+            JustInterval::MajorSixth => Rational::from((5,3)),
+            JustInterval::IMajorSixth => JustInterval::MajorSixth.get_freq_scale().inv(),
+            JustInterval::SeptimalWholeTone => Rational::from((8,7)),
+            JustInterval::ISeptimalWholeTone => JustInterval::SeptimalWholeTone.get_freq_scale().inv(),
+            JustInterval::SeptimalMinorThird => Rational::from((7,6)),
+            JustInterval::ISeptimalMinorThird => JustInterval::SeptimalMinorThird.get_freq_scale().inv(),
+            JustInterval::SeptimalMajorThird => Rational::from((9,7)),
+            JustInterval::ISeptimalMajorThird => JustInterval::SeptimalMajorThird.get_freq_scale().inv(),
+            JustInterval::SeptimalTritone => Rational::from((7,5)),
+            JustInterval::ISeptimalTritone => JustInterval::SeptimalTritone.get_freq_scale().inv(),
+            JustInterval::HarmonicSeventh => Rational::from((7,4)),
+            JustInterval::IHarmonicSeventh => JustInterval::HarmonicSeventh.get_freq_scale().inv(),
+            JustInterval::UndecimalNeutralThird => Rational::from((11,9)),
+            JustInterval::IUndecimalNeutralThird => JustInterval::UndecimalNeutralThird.get_freq_scale().inv(),
+            JustInterval::UndecimalSuperFourth => Rational::from((11,8)),
+            JustInterval::IUndecimalSuperFourth => JustInterval::UndecimalSuperFourth.get_freq_scale().inv(),
+            JustInterval::TridecimalSemitone => Rational::from((13,12)),
+            JustInterval::ITridecimalSemitone => JustInterval::TridecimalSemitone.get_freq_scale().inv(),
+            JustInterval::TridecimalNeutralSixth => Rational::from((13,8)),
+            JustInterval::ITridecimalNeutralSixth => JustInterval::TridecimalNeutralSixth.get_freq_scale().inv(),
         }
     }
     pub fn get_half_steps(&self) -> i32 {
         match self {
             JustInterval::Unison => 0,
+            JustInterval::DiatonicSemitone => 1,
+            JustInterval::IDiatonicSemitone => -JustInterval::DiatonicSemitone.get_half_steps(),
+            JustInterval::MinorWholeTone => 2,
+            JustInterval::IMinorWholeTone => -JustInterval::MinorWholeTone.get_half_steps(),
+            JustInterval::MajorWholeTone => 2,
+            JustInterval::IMajorWholeTone => -JustInterval::MajorWholeTone.get_half_steps(),
+            JustInterval::MinorThird => 3,
+            JustInterval::IMinorThird => -JustInterval::MinorThird.get_half_steps(),
             JustInterval::MajorThird => 4,
             JustInterval::IMajorThird => -JustInterval::MajorThird.get_half_steps(),
             JustInterval::PerfectFourth => 5,
             JustInterval::IPerfectFourth => -JustInterval::PerfectFourth.get_half_steps(),
             JustInterval::PerfectFifth => 7,
             JustInterval::IPerfectFifth => -JustInterval::PerfectFifth.get_half_steps(),
+            JustInterval::MinorSixth => 8,
+            JustInterval::IMinorSixth => -JustInterval::MinorSixth.get_half_steps(),
+            JustInterval::MajorSixth => 9,
+            JustInterval::IMajorSixth => -JustInterval::MajorSixth.get_half_steps(),
+            JustInterval::SeptimalWholeTone => 2,
+            JustInterval::ISeptimalWholeTone => -JustInterval::SeptimalWholeTone.get_half_steps(),
+            JustInterval::SeptimalMinorThird => 3,
+            JustInterval::ISeptimalMinorThird => -JustInterval::SeptimalMinorThird.get_half_steps(),
+            JustInterval::SeptimalMajorThird => 4,
+            JustInterval::ISeptimalMajorThird => -JustInterval::SeptimalMajorThird.get_half_steps(),
+            JustInterval::SeptimalTritone => 6,
+            JustInterval::ISeptimalTritone => -JustInterval::SeptimalTritone.get_half_steps(),
+            JustInterval::HarmonicSeventh => 10,
+            JustInterval::IHarmonicSeventh => -JustInterval::HarmonicSeventh.get_half_steps(),
+            JustInterval::UndecimalNeutralThird => 3,
+            JustInterval::IUndecimalNeutralThird => -JustInterval::UndecimalNeutralThird.get_half_steps(),
+            JustInterval::UndecimalSuperFourth => 6,
+            JustInterval::IUndecimalSuperFourth => -JustInterval::UndecimalSuperFourth.get_half_steps(),
+            JustInterval::TridecimalSemitone => 1,
+            JustInterval::ITridecimalSemitone => -JustInterval::TridecimalSemitone.get_half_steps(),
+            JustInterval::TridecimalNeutralSixth => 8,
+            JustInterval::ITridecimalNeutralSixth => -JustInterval::TridecimalNeutralSixth.get_half_steps(),
         }
     }
+
+    pub fn get_monzo(&self) -> Monzo {
+        Monzo::try_from(&self.get_freq_scale()).unwrap()
+    }
+
+    // largest prime factor of the frequency ratio
+    pub fn get_prime_limit(&self) -> u32 {
+        self.get_monzo().get_prime_limit()
+    }
+
+    // all basis intervals whose ratios only contain primes up to prime_limit
+    pub fn vocabulary(prime_limit: u32) -> Vec<JustInterval> {
+        JustInterval::BASIS.iter()
+            .filter(|interval| interval.get_prime_limit() <= prime_limit)
+            .copied()
+            .collect()
+    }
 }
 
 impl Neg for JustInterval {
     type Output = Self;
     fn neg(self) -> Self::Output {
         match self {
-            JustInterval::Unison         => self,
-            JustInterval::MajorThird     => JustInterval::IMajorThird,
-            JustInterval::IMajorThird    => JustInterval::MajorThird,
-            JustInterval::PerfectFourth  => JustInterval::IPerfectFourth,
-            JustInterval::IPerfectFourth => JustInterval::PerfectFourth,
-            JustInterval::PerfectFifth   => JustInterval::IPerfectFifth,
-            JustInterval::IPerfectFifth  => JustInterval::PerfectFifth
+            JustInterval::Unison                  => self,
+            JustInterval::DiatonicSemitone        => JustInterval::IDiatonicSemitone,
+            JustInterval::IDiatonicSemitone       => JustInterval::DiatonicSemitone,
+            JustInterval::MinorWholeTone          => JustInterval::IMinorWholeTone,
+            JustInterval::IMinorWholeTone         => JustInterval::MinorWholeTone,
+            JustInterval::MajorWholeTone          => JustInterval::IMajorWholeTone,
+            JustInterval::IMajorWholeTone         => JustInterval::MajorWholeTone,
+            JustInterval::MinorThird              => JustInterval::IMinorThird,
+            JustInterval::IMinorThird             => JustInterval::MinorThird,
+            JustInterval::MajorThird              => JustInterval::IMajorThird,
+            JustInterval::IMajorThird             => JustInterval::MajorThird,
+            JustInterval::PerfectFourth           => JustInterval::IPerfectFourth,
+            JustInterval::IPerfectFourth          => JustInterval::PerfectFourth,
+            JustInterval::PerfectFifth            => JustInterval::IPerfectFifth,
+            JustInterval::IPerfectFifth           => JustInterval::PerfectFifth,
+            JustInterval::MinorSixth              => JustInterval::IMinorSixth,
+            JustInterval::IMinorSixth             => JustInterval::MinorSixth,
+            JustInterval::MajorSixth              => JustInterval::IMajorSixth,
+            JustInterval::IMajorSixth             => JustInterval::MajorSixth,
+            JustInterval::SeptimalWholeTone       => JustInterval::ISeptimalWholeTone,
+            JustInterval::ISeptimalWholeTone      => JustInterval::SeptimalWholeTone,
+            JustInterval::SeptimalMinorThird      => JustInterval::ISeptimalMinorThird,
+            JustInterval::ISeptimalMinorThird     => JustInterval::SeptimalMinorThird,
+            JustInterval::SeptimalMajorThird      => JustInterval::ISeptimalMajorThird,
+            JustInterval::ISeptimalMajorThird     => JustInterval::SeptimalMajorThird,
+            JustInterval::SeptimalTritone         => JustInterval::ISeptimalTritone,
+            JustInterval::ISeptimalTritone        => JustInterval::SeptimalTritone,
+            JustInterval::HarmonicSeventh         => JustInterval::IHarmonicSeventh,
+            JustInterval::IHarmonicSeventh        => JustInterval::HarmonicSeventh,
+            JustInterval::UndecimalNeutralThird   => JustInterval::IUndecimalNeutralThird,
+            JustInterval::IUndecimalNeutralThird  => JustInterval::UndecimalNeutralThird,
+            JustInterval::UndecimalSuperFourth    => JustInterval::IUndecimalSuperFourth,
+            JustInterval::IUndecimalSuperFourth   => JustInterval::UndecimalSuperFourth,
+            JustInterval::TridecimalSemitone      => JustInterval::ITridecimalSemitone,
+            JustInterval::ITridecimalSemitone     => JustInterval::TridecimalSemitone,
+            JustInterval::TridecimalNeutralSixth  => JustInterval::ITridecimalNeutralSixth,
+            JustInterval::ITridecimalNeutralSixth => JustInterval::TridecimalNeutralSixth,
         }
     }
 }
@@ -69,33 +229,110 @@ impl Mul<i32> for JustInterval {
 mod tests {
     use super::*;
 
+    const OPTIONS: [JustInterval; 37] = [
+        JustInterval::Unison,
+        JustInterval::DiatonicSemitone, JustInterval::IDiatonicSemitone,
+        JustInterval::MinorWholeTone, JustInterval::IMinorWholeTone,
+        JustInterval::MajorWholeTone, JustInterval::IMajorWholeTone,
+        JustInterval::MinorThird, JustInterval::IMinorThird,
+        JustInterval::MajorThird, JustInterval::IMajorThird,
+        JustInterval::PerfectFourth, JustInterval::IPerfectFourth,
+        JustInterval::PerfectFifth, JustInterval::IPerfectFifth,
+        JustInterval::MinorSixth, JustInterval::IMinorSixth,
+        JustInterval::MajorSixth, JustInterval::IMajorSixth,
+        JustInterval::SeptimalWholeTone, JustInterval::ISeptimalWholeTone,
+        JustInterval::SeptimalMinorThird, JustInterval::ISeptimalMinorThird,
+        JustInterval::SeptimalMajorThird, JustInterval::ISeptimalMajorThird,
+        JustInterval::SeptimalTritone, JustInterval::ISeptimalTritone,
+        JustInterval::HarmonicSeventh, JustInterval::IHarmonicSeventh,
+        JustInterval::UndecimalNeutralThird, JustInterval::IUndecimalNeutralThird,
+        JustInterval::UndecimalSuperFourth, JustInterval::IUndecimalSuperFourth,
+        JustInterval::TridecimalSemitone, JustInterval::ITridecimalSemitone,
+        JustInterval::TridecimalNeutralSixth, JustInterval::ITridecimalNeutralSixth];
+
     #[test]
     fn get_freq_scale() {
-        assert_eq!(JustInterval::MajorThird.get_freq_scale(),     Rational::from((5,4)));
-        assert_eq!(JustInterval::IMajorThird.get_freq_scale(),    Rational::from((4,5)));
-        assert_eq!(JustInterval::PerfectFourth.get_freq_scale(),  Rational::from((4,3)));
-        assert_eq!(JustInterval::IPerfectFourth.get_freq_scale(), Rational::from((3,4)));
-        assert_eq!(JustInterval::PerfectFifth.get_freq_scale(),   Rational::from((3,2)));
-        assert_eq!(JustInterval::IPerfectFifth.get_freq_scale(),  Rational::from((2,3)));
+        assert_eq!(JustInterval::DiatonicSemitone.get_freq_scale(),        Rational::from((16,15)));
+        assert_eq!(JustInterval::IDiatonicSemitone.get_freq_scale(),       Rational::from((15,16)));
+        assert_eq!(JustInterval::MinorWholeTone.get_freq_scale(),          Rational::from((10,9)));
+        assert_eq!(JustInterval::IMinorWholeTone.get_freq_scale(),         Rational::from((9,10)));
+        assert_eq!(JustInterval::MajorWholeTone.get_freq_scale(),          Rational::from((9,8)));
+        assert_eq!(JustInterval::IMajorWholeTone.get_freq_scale(),         Rational::from((8,9)));
+        assert_eq!(JustInterval::MinorThird.get_freq_scale(),              Rational::from((6,5)));
+        assert_eq!(JustInterval::IMinorThird.get_freq_scale(),             Rational::from((5,6)));
+        assert_eq!(JustInterval::MajorThird.get_freq_scale(),              Rational::from((5,4)));
+        assert_eq!(JustInterval::IMajorThird.get_freq_scale(),             Rational::from((4,5)));
+        assert_eq!(JustInterval::PerfectFourth.get_freq_scale(),           Rational::from((4,3)));
+        assert_eq!(JustInterval::IPerfectFourth.get_freq_scale(),          Rational::from((3,4)));
+        assert_eq!(JustInterval::PerfectFifth.get_freq_scale(),            Rational::from((3,2)));
+        assert_eq!(JustInterval::IPerfectFifth.get_freq_scale(),           Rational::from((2,3)));
+        assert_eq!(JustInterval::MinorSixth.get_freq_scale(),              Rational::from((8,5)));
+        assert_eq!(JustInterval::IMinorSixth.get_freq_scale(),             Rational::from((5,8)));
+        assert_eq!(JustInterval::MajorSixth.get_freq_scale(),              Rational::from((5,3)));
+        assert_eq!(JustInterval::IMajorSixth.get_freq_scale(),             Rational::from((3,5)));
+        assert_eq!(JustInterval::SeptimalWholeTone.get_freq_scale(),       Rational::from((8,7)));
+        assert_eq!(JustInterval::ISeptimalWholeTone.get_freq_scale(),      Rational::from((7,8)));
+        assert_eq!(JustInterval::SeptimalMinorThird.get_freq_scale(),      Rational::from((7,6)));
+        assert_eq!(JustInterval::ISeptimalMinorThird.get_freq_scale(),     Rational::from((6,7)));
+        assert_eq!(JustInterval::SeptimalMajorThird.get_freq_scale(),      Rational::from((9,7)));
+        assert_eq!(JustInterval::ISeptimalMajorThird.get_freq_scale(),     Rational::from((7,9)));
+        assert_eq!(JustInterval::SeptimalTritone.get_freq_scale(),         Rational::from((7,5)));
+        assert_eq!(JustInterval::ISeptimalTritone.get_freq_scale(),        Rational::from((5,7)));
+        assert_eq!(JustInterval::HarmonicSeventh.get_freq_scale(),         Rational::from((7,4)));
+        assert_eq!(JustInterval::IHarmonicSeventh.get_freq_scale(),        Rational::from((4,7)));
+        assert_eq!(JustInterval::UndecimalNeutralThird.get_freq_scale(),   Rational::from((11,9)));
+        assert_eq!(JustInterval::IUndecimalNeutralThird.get_freq_scale(),  Rational::from((9,11)));
+        assert_eq!(JustInterval::UndecimalSuperFourth.get_freq_scale(),    Rational::from((11,8)));
+        assert_eq!(JustInterval::IUndecimalSuperFourth.get_freq_scale(),   Rational::from((8,11)));
+        assert_eq!(JustInterval::TridecimalSemitone.get_freq_scale(),      Rational::from((13,12)));
+        assert_eq!(JustInterval::ITridecimalSemitone.get_freq_scale(),     Rational::from((12,13)));
+        assert_eq!(JustInterval::TridecimalNeutralSixth.get_freq_scale(),  Rational::from((13,8)));
+        assert_eq!(JustInterval::ITridecimalNeutralSixth.get_freq_scale(), Rational::from((8,13)));
     }
 
     #[test]
     fn get_half_steps() {
-        assert_eq!(JustInterval::MajorThird.get_half_steps(),      4);
-        assert_eq!(JustInterval::IMajorThird.get_half_steps(),    -4);
-        assert_eq!(JustInterval::PerfectFourth.get_half_steps(),   5);
-        assert_eq!(JustInterval::IPerfectFourth.get_half_steps(), -5);
-        assert_eq!(JustInterval::PerfectFifth.get_half_steps(),    7);
-        assert_eq!(JustInterval::IPerfectFifth.get_half_steps(),  -7);
+        assert_eq!(JustInterval::DiatonicSemitone.get_half_steps(),          1);
+        assert_eq!(JustInterval::IDiatonicSemitone.get_half_steps(),        -1);
+        assert_eq!(JustInterval::MinorWholeTone.get_half_steps(),            2);
+        assert_eq!(JustInterval::IMinorWholeTone.get_half_steps(),          -2);
+        assert_eq!(JustInterval::MajorWholeTone.get_half_steps(),            2);
+        assert_eq!(JustInterval::IMajorWholeTone.get_half_steps(),          -2);
+        assert_eq!(JustInterval::MinorThird.get_half_steps(),                3);
+        assert_eq!(JustInterval::IMinorThird.get_half_steps(),              -3);
+        assert_eq!(JustInterval::MajorThird.get_half_steps(),                4);
+        assert_eq!(JustInterval::IMajorThird.get_half_steps(),              -4);
+        assert_eq!(JustInterval::PerfectFourth.get_half_steps(),             5);
+        assert_eq!(JustInterval::IPerfectFourth.get_half_steps(),           -5);
+        assert_eq!(JustInterval::PerfectFifth.get_half_steps(),              7);
+        assert_eq!(JustInterval::IPerfectFifth.get_half_steps(),            -7);
+        assert_eq!(JustInterval::MinorSixth.get_half_steps(),                8);
+        assert_eq!(JustInterval::IMinorSixth.get_half_steps(),              -8);
+        assert_eq!(JustInterval::MajorSixth.get_half_steps(),                9);
+        assert_eq!(JustInterval::IMajorSixth.get_half_steps(),              -9);
+        assert_eq!(JustInterval::SeptimalWholeTone.get_half_steps(),         2);
+        assert_eq!(JustInterval::ISeptimalWholeTone.get_half_steps(),       -2);
+        assert_eq!(JustInterval::SeptimalMinorThird.get_half_steps(),        3);
+        assert_eq!(JustInterval::ISeptimalMinorThird.get_half_steps(),      -3);
+        assert_eq!(JustInterval::SeptimalMajorThird.get_half_steps(),        4);
+        assert_eq!(JustInterval::ISeptimalMajorThird.get_half_steps(),      -4);
+        assert_eq!(JustInterval::SeptimalTritone.get_half_steps(),           6);
+        assert_eq!(JustInterval::ISeptimalTritone.get_half_steps(),         -6);
+        assert_eq!(JustInterval::HarmonicSeventh.get_half_steps(),          10);
+        assert_eq!(JustInterval::IHarmonicSeventh.get_half_steps(),        -10);
+        assert_eq!(JustInterval::UndecimalNeutralThird.get_half_steps(),     3);
+        assert_eq!(JustInterval::IUndecimalNeutralThird.get_half_steps(),   -3);
+        assert_eq!(JustInterval::UndecimalSuperFourth.get_half_steps(),      6);
+        assert_eq!(JustInterval::IUndecimalSuperFourth.get_half_steps(),    -6);
+        assert_eq!(JustInterval::TridecimalSemitone.get_half_steps(),        1);
+        assert_eq!(JustInterval::ITridecimalSemitone.get_half_steps(),      -1);
+        assert_eq!(JustInterval::TridecimalNeutralSixth.get_half_steps(),    8);
+        assert_eq!(JustInterval::ITridecimalNeutralSixth.get_half_steps(),  -8);
     }
 
     #[test]
     fn negate() {
-        let options = [JustInterval::Unison,
-                       JustInterval::MajorThird, JustInterval::IMajorThird,
-                       JustInterval::PerfectFourth, JustInterval::IPerfectFourth,
-                       JustInterval::PerfectFifth, JustInterval::IPerfectFifth];
-        for value in options {
+        for value in OPTIONS {
             let negated = -value;
             assert_eq!(value, -negated);
             assert_eq!(value.get_half_steps(), -(negated.get_half_steps()));
@@ -105,18 +342,66 @@ mod tests {
 
     #[test]
     fn multiply() {
-        let options = [JustInterval::Unison,
-                       JustInterval::MajorThird, JustInterval::IMajorThird,
-                       JustInterval::PerfectFourth, JustInterval::IPerfectFourth,
-                       JustInterval::PerfectFifth, JustInterval::IPerfectFifth];
-        for value in options {
-            for factor in 1..3 {
-                assert_eq!(value*factor, value);
-                assert_eq!(value*(-factor), -value);
+        for value in OPTIONS {
+            for factor in -2..3 {
+                let reference = match factor.cmp(&0) {
+                    Ordering::Greater => value,
+                    Ordering::Equal   => JustInterval::Unison,
+                    Ordering::Less    => -value
+                };
+                assert_eq!(value*factor, reference);
             }
-            for factor in [0] {
-                assert_eq!(value*factor, JustInterval::Unison);
+        }
+    }
+
+    #[test]
+    fn basis() {
+        // the basis holds every interval exactly once, either ascending or inverted
+        for value in OPTIONS {
+            let nbasis = JustInterval::BASIS.iter()
+                .filter(|&&basis| basis == value || -basis == value)
+                .count();
+            match value {
+                JustInterval::Unison => assert_eq!(nbasis, 0),
+                _ => assert_eq!(nbasis, 1)
             }
         }
+        for basis in JustInterval::BASIS {
+            assert!(basis.get_half_steps() > 0);
+        }
+    }
+
+    #[test]
+    fn get_monzo() {
+        assert_eq!(JustInterval::Unison.get_monzo(),          Monzo::unison());
+        assert_eq!(JustInterval::PerfectFifth.get_monzo(),    Monzo::new(vec![-1, 1]));
+        assert_eq!(JustInterval::IMajorThird.get_monzo(),     Monzo::new(vec![2, 0, -1]));
+        assert_eq!(JustInterval::HarmonicSeventh.get_monzo(), Monzo::new(vec![-2, 0, 0, 1]));
+        for value in OPTIONS {
+            assert_eq!(value.get_monzo().to_rational(), value.get_freq_scale());
+        }
+    }
+
+    #[test]
+    fn get_prime_limit() {
+        assert_eq!(JustInterval::Unison.get_prime_limit(),                  1);
+        assert_eq!(JustInterval::MajorWholeTone.get_prime_limit(),          3);
+        assert_eq!(JustInterval::IMinorSixth.get_prime_limit(),             5);
+        assert_eq!(JustInterval::HarmonicSeventh.get_prime_limit(),         7);
+        assert_eq!(JustInterval::IUndecimalSuperFourth.get_prime_limit(),  11);
+        assert_eq!(JustInterval::TridecimalNeutralSixth.get_prime_limit(), 13);
+    }
+
+    #[test]
+    fn vocabulary() {
+        assert_eq!(JustInterval::vocabulary(3), vec![JustInterval::MajorWholeTone,
+                                                     JustInterval::PerfectFourth,
+                                                     JustInterval::PerfectFifth]);
+        assert_eq!(JustInterval::vocabulary(5).len(), 9);
+        assert_eq!(JustInterval::vocabulary(7).len(), 14);
+        assert_eq!(JustInterval::vocabulary(11).len(), 16);
+        assert_eq!(JustInterval::vocabulary(13), JustInterval::BASIS.to_vec());
+        assert!(JustInterval::vocabulary(7).contains(&JustInterval::HarmonicSeventh));
+        assert!(!JustInterval::vocabulary(7).contains(&JustInterval::UndecimalSuperFourth));
     }
 }
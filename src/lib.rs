@@ -0,0 +1,45 @@
+//! Just intonated interval sequences that drift in tuning.
+//!
+//! The library finds interval sets that span a number of half steps while scaling the
+//! frequency by a target ratio, orders them into sequences and renders them as notes.
+
+pub mod error;
+pub mod monzo;
+pub mod just_intervals;
+pub mod intervals;
+pub mod interval_vocabulary;
+pub mod interval_sequences;
+pub mod heji;
+pub mod notevalues;
+pub mod notes;
+pub mod interval_set;
+pub mod commas;
+pub mod drift_search;
+pub mod target;
+pub mod reference;
+pub mod note_range;
+pub mod midi;
+pub mod audio;
+pub mod score;
+pub mod musicxml;
+pub mod lilypond;
+pub mod abc;
+pub mod scala;
+pub mod report;
+mod vector_distributor;
+
+pub use crate::error::Error;
+pub use crate::monzo::Monzo;
+pub use crate::just_intervals::JustInterval;
+pub use crate::intervals::Interval;
+pub use crate::interval_vocabulary::{builtin_vocabulary, load_vocabulary};
+pub use crate::interval_sequences::{IntervalSequence, SequenceNote};
+pub use crate::notevalues::NoteValues;
+pub use crate::notes::Note;
+pub use crate::interval_set::IntervalSet;
+pub use crate::commas::{Comma, comma_basis};
+pub use crate::drift_search::{search_drift, pareto_front};
+pub use crate::target::Target;
+pub use crate::reference::Reference;
+pub use crate::note_range::NoteRange;
+pub use crate::vector_distributor::SequenceOrder;
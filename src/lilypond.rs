@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::Path;
+use crate::error::Error;
+use crate::interval_sequences::SequenceNote;
+use crate::intervals::Interval;
+use crate::score::{Annotation, annotations, is_treble, measures, part_events};
+
+// absolute pitch of the note, c is the C below middle C
+fn pitch(note: &SequenceNote) -> String {
+    let note = &note.note;
+    let accidental = note.get_accidental();
+    let octave = note.get_octave() - 3;
+    format!("{}{}{}", note.get_letter().to_str().to_lowercase(),
+            if accidental > 0 {"is"} else {"es"}.repeat(accidental.unsigned_abs() as usize),
+            if octave > 0 {"'"} else {","}.repeat(octave.unsigned_abs() as usize))
+}
+
+fn duration(beats: usize) -> &'static str {
+    match beats {
+        1 => "4",
+        2 => "2",
+        3 => "2.",
+        _ => "1"
+    }
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Score of the sequence with one staff per instrument and the annotations as lyrics under the notes.
+// Tied notes and rests take no lyrics, notes without annotation an empty one.
+pub fn to_lilypond(notes: &[SequenceNote], intervals: &[Interval], split: bool, annotation: Annotation) -> String {
+    let texts = annotations(intervals, notes, annotation);
+    let mut ly = String::from("\\version \"2.24.0\"\n\n\\score {\n  <<\n");
+    for (ipart, events) in part_events(notes.len(), split).iter().enumerate() {
+        ly.push_str(&format!("    \\new Staff \\with {{ instrumentName = \"Instrument {}\" }} {{\n", ipart+1));
+        ly.push_str(&format!("      \\clef {}\n", if is_treble(events, notes) {"treble"} else {"bass"}));
+        ly.push_str("      \\time 4/4\n");
+        for pieces in measures(events) {
+            let notation: Vec<String> = pieces.iter()
+                .map(|piece| match piece.note {
+                    Some(inote) => format!("{}{}{}", pitch(&notes[inote]), duration(piece.beats), if piece.tied_to {" ~"} else {""}),
+                    None => format!("r{}", duration(piece.beats))
+                })
+                .collect();
+            ly.push_str(&format!("      {} |\n", notation.join(" ")));
+        }
+        ly.push_str("      \\bar \"|.\"\n    }\n");
+        let lyrics: Vec<String> = events.iter()
+            .filter_map(|event| event.note)
+            .map(|inote| quoted(texts[inote].as_deref().unwrap_or("")))
+            .collect();
+        ly.push_str(&format!("    \\addlyrics {{ {} }}\n", lyrics.join(" ")));
+    }
+    ly.push_str("  >>\n  \\layout { }\n}\n");
+    ly
+}
+
+pub fn write_lilypond(path: &Path, notes: &[SequenceNote], intervals: &[Interval], split: bool,
+                      annotation: Annotation) -> Result<(), Error> {
+    fs::write(path, to_lilypond(notes, intervals, split, annotation))
+        .map_err(|err| Error::Export(format!("Cannot write LilyPond file {}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interval_sequences::test_notes;
+    use crate::notes::Note;
+    use crate::score::test_score;
+
+    #[test]
+    fn pitches() {
+        let (notes, _) = test_score(Note::new("C", 3));
+        assert_eq!(notes.iter().map(pitch).collect::<Vec<_>>(), vec!["c", "d", "b,", "dis", "cis"]);
+        let notes = test_notes(&[], Note::new("Ebb", 5));
+        assert_eq!(pitch(&notes[0]), "eeses''");
+        assert_eq!(quoted("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn single_staff() {
+        let (notes, intervals) = test_score(Note::new("C", 4));
+        assert_eq!(to_lilypond(&notes, &intervals, false, Annotation::Cents), "\
+\\version \"2.24.0\"
+
+\\score {
+  <<
+    \\new Staff \\with { instrumentName = \"Instrument 1\" } {
+      \\clef treble
+      \\time 4/4
+      c'4 d'4 b4 dis'4 |
+      cis'4 r2. |
+      \\bar \"|.\"
+    }
+    \\addlyrics { \"+0.0\" \"+3.9\" \"-11.7\" \"-25.4\" \"-7.8\" }
+  >>
+  \\layout { }
+}
+");
+    }
+
+    #[test]
+    fn split_staves() {
+        let (notes, intervals) = test_score(Note::new("C", 3));
+        assert_eq!(to_lilypond(&notes, &intervals, true, Annotation::Intervals), "\
+\\version \"2.24.0\"
+
+\\score {
+  <<
+    \\new Staff \\with { instrumentName = \"Instrument 1\" } {
+      \\clef bass
+      \\time 4/4
+      c2 b,2 |
+      cis4 r2. |
+      \\bar \"|.\"
+    }
+    \\addlyrics { \"\" \"IMinorThird\" \"IMinorWholeTone\" }
+    \\new Staff \\with { instrumentName = \"Instrument 2\" } {
+      \\clef bass
+      \\time 4/4
+      r4 d2 dis4 ~ |
+      dis4 r2. |
+      \\bar \"|.\"
+    }
+    \\addlyrics { \"MajorWholeTone\" \"MajorThird\" }
+  >>
+  \\layout { }
+}
+");
+    }
+}
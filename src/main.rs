@@ -1,29 +1,39 @@
-mod just_intervals;
-mod interval_sequences;
-mod notevalues;
-mod notes;
-mod interval_set;
-mod vector_distributor;
-
+use std::path::PathBuf;
+use std::process;
 use clap::Parser;
-use crate::notes::Note;
-use crate::interval_set::IntervalSet;
+use just_out_of_tune::audio::{MAX_SAMPLE_RATE, RenderOptions, Timbre, write_wav};
+use just_out_of_tune::abc::write_abc;
+use just_out_of_tune::lilypond::write_lilypond;
+use just_out_of_tune::musicxml::write_musicxml;
+use just_out_of_tune::scala::write_scala;
+use just_out_of_tune::score::Annotation;
+use just_out_of_tune::midi::{MidiMode, MidiOptions, sequence_tracks, write_midi};
+use just_out_of_tune::report::{SCHEMA_VERSION, CommaEntry, CountEntry, Format, IntervalEntry, NoteEntry, Parameters, Report,
+                               SequenceEntry, SetEntry};
+use just_out_of_tune::{Error, Note, NoteRange, IntervalSet, IntervalSequence, SequenceNote, SequenceOrder, Target, Reference, comma_basis,
+                       search_drift, pareto_front, builtin_vocabulary, load_vocabulary};
+use just_out_of_tune::interval_set::MAX_LISTED_INTERVALS;
 
 /// Compute just intonated interval sequences that drift in tuning
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None,
+          after_help = "Exit codes: 1 export failed, 2 invalid input, 3 no sequence reaches the target, 4 search limit exceeded")]
 struct Args {
-    /// Frequency scaling parameter
-    #[arg(long, default_value_t = 1.0)]
-    freq_scale: f64,
+    /// Target frequency scaling as ratio (81/80, 1.05), cents (+21.5c) or final pitch (A4@435Hz)
+    #[arg(long, default_value = "1", allow_hyphen_values = true)]
+    freq_scale: Target,
 
     /// Error of freq_scale in cents (1/100 half step)
     #[arg(long, default_value_t = 1.0)]
     freq_scale_err: f64,
 
-    /// Number of half steps
-    #[arg(long, default_value_t = 0)]
-    nhalf_steps: i32,
+    /// Concert pitch of the absolute frequencies and target pitches as note=Hz
+    #[arg(long, default_value = "A4=440")]
+    reference: Reference,
+
+    /// Number of half steps, implied by a target pitch [default: 0]
+    #[arg(long, allow_hyphen_values = true)]
+    nhalf_steps: Option<i32>,
 
     /// Starting note name
     #[arg(long, default_value_t = String::from("C"))]
@@ -36,129 +46,439 @@ struct Args {
     /// Split notes for instruments
     #[arg(long, default_value_t = false)]
     split_note_sequence: bool,
+
+    /// Playable range of the notes as lowest..highest note (C3..G4), given twice for the two instruments of a split sequence.
+    /// The intervals are reordered or played an octave apart to stay within it
+    #[arg(long)]
+    range: Vec<NoteRange>,
+
+    /// Order of the intervals: even (spread evenly), euclidean (nested Bjorklund rhythms), random[:SEED] (shuffled),
+    /// balanced (close to the starting note) or grouped (ascending ones first)
+    #[arg(long, default_value = "even")]
+    ordering: SequenceOrder,
+
+    /// Output format: text, json (versioned schema, see README) or csv (section,index,field,value rows)
+    #[arg(long, default_value = "text")]
+    format: Format,
+
+    /// Largest prime allowed in the interval ratios (e.g. 5, 7, 11, 13)
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(3..))]
+    prime_limit: u32,
+
+    /// Interval vocabulary file (.toml, .json or Scala .scl) replacing the built-in intervals
+    #[arg(long, conflicts_with = "prime_limit")]
+    intervals: Option<PathBuf>,
+
+    /// Largest number of intervals the sequence search may use
+    #[arg(long, default_value_t = 64)]
+    max_intervals: i32,
+
+    /// List every half step satisfying interval set with up to this many intervals
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..=i64::from(MAX_LISTED_INTERVALS)))]
+    list_hstep_solutions: Option<i32>,
+
+    /// Print every sequence that is not beaten in both length and scaling error instead of the shortest one
+    #[arg(long, default_value_t = false)]
+    pareto: bool,
+
+    /// Annotate the notes with Helmholtz-Ellis comma accidentals (v/^ syntonic, L/7 septimal, ^11, v13),
+    /// the notes then keep their spelling instead of being respelled beyond double sharps and flats
+    #[arg(long, default_value_t = false)]
+    heji: bool,
+
+    /// Write the note sequence as Standard MIDI File, detuned to the just frequencies by pitch bends
+    #[arg(long, conflicts_with = "pareto")]
+    midi: Option<PathBuf>,
+
+    /// Detuning of the MIDI notes: bend (pitch bend per instrument), mts (tuning change per key) or mpe (channel per note)
+    #[arg(long, default_value = "bend")]
+    midi_mode: MidiMode,
+
+    /// Pitch bend range of the MIDI instruments in half steps
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=24))]
+    bend_range: u8,
+
+    /// Tempo of the MIDI file in beats per minute
+    #[arg(long, value_parser = positive, default_value_t = 60.0)]
+    tempo: f64,
+
+    /// Duration of a note of the MIDI file in beats
+    #[arg(long, value_parser = positive, default_value_t = 1.0)]
+    note_duration: f64,
+
+    /// Write the note sequence as MusicXML score with the deviation in cents above each note
+    #[arg(long, conflicts_with = "pareto")]
+    musicxml: Option<PathBuf>,
+
+    /// Write the note sequence as LilyPond score
+    #[arg(long, conflicts_with = "pareto")]
+    lilypond: Option<PathBuf>,
+
+    /// Write the note sequence as ABC tune
+    #[arg(long, conflicts_with = "pareto")]
+    abc: Option<PathBuf>,
+
+    /// Lyrics under the notes of the LilyPond and ABC scores: cents (deviation from 12-TET) or intervals
+    #[arg(long, default_value = "cents")]
+    annotation: Annotation,
+
+    /// Write the pitches visited by the sequence as Scala scale, with a .kbm keyboard mapping next to it
+    #[arg(long, conflicts_with = "pareto")]
+    scala: Option<PathBuf>,
+
+    /// Render the note sequence at its just frequencies to a WAV file
+    #[arg(long, conflicts_with = "pareto")]
+    render: Option<PathBuf>,
+
+    /// Waveform of the rendered notes: sine, sawtooth or additive
+    #[arg(long, default_value = "sine")]
+    timbre: Timbre,
+
+    /// Sample rate of the rendered WAV file in Hz
+    #[arg(long, default_value_t = 44100, value_parser = clap::value_parser!(u32).range(1..=i64::from(MAX_SAMPLE_RATE)))]
+    sample_rate: u32,
+
+    /// Duration of a rendered note in seconds
+    #[arg(long, default_value_t = 1.0, value_parser = positive)]
+    note_length: f64,
+
+    /// Fade in of the rendered notes in seconds
+    #[arg(long, default_value_t = 0.02, value_parser = non_negative)]
+    attack: f64,
+
+    /// Fade out of the rendered notes in seconds
+    #[arg(long, default_value_t = 0.1, value_parser = non_negative)]
+    release: f64,
+
+    /// Hold the 12-TET starting note below the rendered sequence
+    #[arg(long, default_value_t = false)]
+    drone: bool,
+}
+
+// values of durations and tempi, checked while parsing so that no search runs with them
+fn positive(s: &str) -> Result<f64, Error> {
+    match s.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+        _ => Err(Error::InvalidOption(format!("'{}' is not a positive number", s)))
+    }
+}
+
+fn non_negative(s: &str) -> Result<f64, Error> {
+    match s.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+        _ => Err(Error::InvalidOption(format!("'{}' is not a number of at least 0", s)))
+    }
 }
 
 fn main() {
     // command line parsing
     let args = Args::parse();
+    if let Err(err) = run(&args) {
+        eprintln!("error: {}", err);
+        process::exit(err.exit_code());
+    }
+}
 
-    println!("Starting out-of-tune sequence search with:");
-    println!("   Number of half steps:      {:10}", args.nhalf_steps);
-    println!("   Target frequency scaling:  {:10.3}", args.freq_scale);
-    println!("   Max scaling error (cents): {:10.3}", args.freq_scale_err);
-    println!("   Starting note and octave   {:>9}{:1}", args.starting_note, args.starting_octave);
-    println!("   Split note sequence        {:>10}", args.split_note_sequence);
-    println!();
+fn run(args: &Args) -> Result<(), Error> {
+    let starting_note = Note::try_new(&args.starting_note, args.starting_octave)?;
+    let nhalf_steps = match (args.nhalf_steps, args.freq_scale.get_half_steps(&starting_note)) {
+        (Some(nhalf_steps), Some(target_half_steps)) if nhalf_steps != target_half_steps => {
+            return Err(Error::InvalidTarget(format!("The target {} lies {} half steps from the starting note, not {}",
+                                                    args.freq_scale, target_half_steps, nhalf_steps)));
+        }
+        (nhalf_steps, target_half_steps) => nhalf_steps.or(target_half_steps).unwrap_or(0)
+    };
+    let instruments = if args.split_note_sequence {2} else {1};
+    if args.range.len() > instruments {
+        return Err(Error::InvalidOption(format!("{} ranges given for {} instrument(s)", args.range.len(), instruments)));
+    }
+    let vocabulary = match &args.intervals {
+        Some(path) => load_vocabulary(path)?,
+        None => builtin_vocabulary(args.prime_limit)
+    };
+    // no sequence of the allowed length spans more half steps
+    let largest_steps = vocabulary.iter().map(|interval| i64::from(interval.get_half_steps().abs())).max().unwrap_or(0);
+    if i64::from(nhalf_steps).abs() > i64::from(args.max_intervals)*largest_steps {
+        return Err(Error::InvalidOption(format!("{} half steps cannot be spanned by {} intervals of at most {} half steps",
+                                                nhalf_steps, args.max_intervals, largest_steps)));
+    }
 
-    // push the half step optimized interval set to the interval list
-    println!("Searching for half step satisfying sequence:");
-    let hstep_satis_set = IntervalSet::new_with_hstep(args.nhalf_steps);
-    let hstep_satis_seq = hstep_satis_set.to_interval_sequence();
-    print!("   Number of half steps:    {:10} = ", hstep_satis_set.get_half_steps());
-    for iinterval in &hstep_satis_seq.intervals {
-        print!("{:4}", iinterval.get_half_steps());
+    // the half step optimized interval set and the commas that change the frequency without changing the half steps
+    let hstep_satis_set = IntervalSet::new_with_hstep(nhalf_steps, &vocabulary)?;
+    let hstep_solutions = args.list_hstep_solutions
+        .map(|max_intervals| IntervalSet::all_with_hstep(nhalf_steps, &vocabulary, max_intervals))
+        .transpose()?;
+    let commas = comma_basis(&vocabulary);
+
+    let target_freq_scale_cent = args.freq_scale.cents(&starting_note, &args.reference);
+    let mut report = Report {
+        schema_version: SCHEMA_VERSION,
+        parameters: Parameters {
+            half_steps: nhalf_steps,
+            target: args.freq_scale.to_string(),
+            target_cents: target_freq_scale_cent,
+            max_err_cents: args.freq_scale_err,
+            starting_note: starting_note.to_string().trim().to_string(),
+            reference: args.reference.to_string(),
+            split_note_sequence: args.split_note_sequence,
+            ranges: args.range.iter().map(NoteRange::to_string).collect(),
+            ordering: args.ordering.to_string(),
+            max_intervals: args.max_intervals,
+            list_hstep_solutions: args.list_hstep_solutions,
+            prime_limit: args.intervals.is_none().then_some(args.prime_limit),
+            vocabulary_file: args.intervals.as_ref().map(|path| path.display().to_string())
+        },
+        vocabulary: vocabulary.iter().map(IntervalEntry::new).collect(),
+        half_step_set: SetEntry::new(&hstep_satis_set, None),
+        half_step_solutions: hstep_solutions.map(|sets| sets.iter().map(|set| SetEntry::new(set, None)).collect()),
+        commas: commas.iter().map(CommaEntry::new).collect(),
+        pareto_front: None,
+        sequence: None
+    };
+    // the text output shows the setup while the search runs
+    if args.format == Format::Text {
+        print_setup(&report);
     }
-    println!();
-    print!("   frequency scaling:       {:10} = ", hstep_satis_set.get_freq_scale());
-    for iinterval in &hstep_satis_seq.intervals {
-        print!("{:4}", iinterval.get_freq_scale());
+
+    // search the shortest interval set that reaches the half steps and the frequency scaling
+    let searched_entry = |interval_set: &IntervalSet| {
+        SetEntry::new(interval_set, Some(args.freq_scale.err_cents(&interval_set.get_freq_scale(), &starting_note, &args.reference)))
+    };
+    let mut found = None;
+    if args.pareto {
+        let front = pareto_front(&vocabulary, nhalf_steps, target_freq_scale_cent,
+                                 args.freq_scale_err, args.max_intervals)?;
+        report.pareto_front = Some(front.iter().map(|(interval_set, _)| searched_entry(interval_set)).collect());
+    } else {
+        let interval_set = search_drift(&vocabulary, nhalf_steps, target_freq_scale_cent,
+                                        args.freq_scale_err, args.max_intervals)?;
+        let interval_seq = match args.range.is_empty() {
+            true => interval_set.to_interval_sequence_ordered(args.ordering),
+            false => interval_set.to_interval_sequence_in_range(starting_note, &args.range, args.ordering)?
+        };
+        // the HEJI signs only apply to notes that are not respelled
+        let annotated_notes = match args.heji {
+            true => interval_seq.to_heji_notes(starting_note, &args.reference)?,
+            false => interval_seq.to_annotated_notes(starting_note, &args.reference)?
+        };
+        // the set lists the intervals in the order of the sequence, which the ranges may change
+        let mut set = searched_entry(&interval_set);
+        set.intervals = interval_seq.intervals.iter().map(IntervalEntry::new).collect();
+        report.sequence = Some(SequenceEntry {
+            set,
+            exact_hit: args.freq_scale.is_hit(&interval_set.get_freq_scale(), &starting_note, &args.reference),
+            notes: annotated_notes.iter().enumerate()
+                .map(|(inote, note)| NoteEntry::new(inote, note, inote.checked_sub(1).map(|iinterval| &interval_seq.intervals[iinterval])))
+                .collect()
+        });
+        found = Some((interval_seq, annotated_notes));
+    }
+    match args.format {
+        Format::Text => print_result(&report, args.heji),
+        Format::Json => print_json(&report)?,
+        Format::Csv => print!("{}", report.to_csv())
+    }
+
+    if let Some((interval_seq, annotated_notes)) = &found {
+        write_exports(args, interval_seq, starting_note, annotated_notes)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn print_json(report: &Report) -> Result<(), Error> {
+    println!("{}", report.to_json());
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json(_report: &Report) -> Result<(), Error> {
+    Err(Error::InvalidOption(String::from("JSON output needs the serde feature")))
+}
+
+fn print_counts(counts: &[CountEntry]) {
+    for count in counts {
+        print!(" {}*{}", count.count, count.name);
     }
     println!();
+}
+
+// notes of the sequence, 20 sequence positions per line
+fn print_notes(notes: &[NoteEntry], printed: impl Fn(usize) -> bool) {
+    for (inote, note) in notes.iter().enumerate() {
+        if inote % 20 == 0 {
+            println!();
+        }
+        if printed(inote) {
+            print!(" {:>3}", note.note);
+        }
+    }
     println!();
+}
 
-    // Create the two scaling interval sets
-    println!("Searching for scaling sequences:");
-    let scaling_sets = IntervalSet::new_with_freq_scale();
-    let scaling_seqs = (scaling_sets.0.to_interval_sequence(), scaling_sets.1.to_interval_sequence());
-    println!("   Downscaling sequence:");
-    print!("      Number of half steps: {:10} = ", scaling_sets.0.get_half_steps());
-    for iinterval in &scaling_seqs.0.intervals {
-        print!("{:4}", iinterval.get_half_steps());
+fn print_setup(report: &Report) {
+    let parameters = &report.parameters;
+    println!("Starting out-of-tune sequence search with:");
+    println!("   Number of half steps:      {:10}", parameters.half_steps);
+    println!("   Target frequency scaling:  {:>10}", parameters.target);
+    println!("   Max scaling error (cents): {:10.3}", parameters.max_err_cents);
+    println!("   Starting note and octave   {:>10}", parameters.starting_note);
+    println!("   Reference pitch            {:>10}", parameters.reference);
+    println!("   Split note sequence        {:>10}", parameters.split_note_sequence);
+    println!("   Interval ordering          {:>10}", parameters.ordering);
+    if !parameters.ranges.is_empty() {
+        println!("   Note ranges                {:>10}", parameters.ranges.join(", "));
+    }
+    println!("   Max number of intervals    {:>10}", parameters.max_intervals);
+    match (&parameters.vocabulary_file, parameters.prime_limit) {
+        (Some(path), _) => println!("   Interval vocabulary        {:>10}", path),
+        (None, prime_limit) => println!("   Prime limit                {:>10}", prime_limit.unwrap_or(0))
     }
     println!();
-    print!("      frequency scaling:    {:10} = ", scaling_sets.0.get_freq_scale());
-    for iinterval in &scaling_seqs.0.intervals {
-        print!("{:4}", iinterval.get_freq_scale());
+
+    println!("Interval vocabulary:");
+    for interval in &report.vocabulary {
+        println!("   {:<26} {:>10} {:4}", interval.name, interval.ratio, interval.half_steps);
     }
     println!();
-    println!("   Upscaling sequence:");
-    print!("      Number of half steps: {:10} = ", scaling_sets.1.get_half_steps());
-    for iinterval in &scaling_seqs.1.intervals {
-        print!("{:4}", iinterval.get_half_steps());
+
+    println!("Searching for half step satisfying sequence:");
+    let hstep_satis_set = &report.half_step_set;
+    print!("   Number of half steps:    {:10} = ", hstep_satis_set.half_steps);
+    for interval in &hstep_satis_set.intervals {
+        print!("{:4}", interval.half_steps);
     }
     println!();
-    print!("      frequency scaling:    {:10} = ", scaling_sets.1.get_freq_scale());
-    for iinterval in &scaling_seqs.1.intervals {
-        print!("{:4}", iinterval.get_freq_scale());
+    print!("   frequency scaling:       {:>10} = ", hstep_satis_set.ratio);
+    for interval in &hstep_satis_set.intervals {
+        print!("{:>4}", interval.ratio);
     }
     println!();
     println!();
 
-    // start building the interval set
-    let mut interval_set = IntervalSet::new_empty();
-    interval_set.add(&hstep_satis_set);
-    // compute the scaling error
-    let to_cent = | x: f64 | -> f64 {1200.0*x.abs().log2()};
-    let target_freq_scale_cent = to_cent(args.freq_scale);
-    let mut set_freq_scale_cent = to_cent(interval_set.get_freq_scale().to_f64());
-    let mut scale_err_cent = (target_freq_scale_cent-set_freq_scale_cent).abs();
-
-    loop {
-        let freq_scale = interval_set.get_freq_scale();
-        if freq_scale == args.freq_scale ||
-           scale_err_cent <= args.freq_scale_err {
-            break;
-        } else if freq_scale > args.freq_scale {
-            // add interval sets that add 0 half steps until the freq_scale is smaller than the target
-            interval_set.add(&scaling_sets.0);
-        } else {
-            // add interval sets that add 0 half steps until the freq_scale is larger than the target
-            interval_set.add(&scaling_sets.1);
+    if let Some(solutions) = &report.half_step_solutions {
+        println!("All half step satisfying sets with up to {} intervals:", parameters.list_hstep_solutions.unwrap_or(0));
+        for interval_set in solutions {
+            print!("   {:4} intervals, frequency scaling {:>16} =", interval_set.num_intervals, interval_set.ratio);
+            print_counts(&interval_set.counts);
         }
-        set_freq_scale_cent = to_cent(interval_set.get_freq_scale().to_f64());
-        scale_err_cent = (target_freq_scale_cent-set_freq_scale_cent).abs();
+        println!();
     }
 
-    println!("Found Sequence:");
-    println!("   Number of intervals:   {}", interval_set.num_intervals());
-    println!("   Scaling frequency:     {:}", interval_set.get_freq_scale().to_f64());
-    println!("   Scaling error (cents): {:}", scale_err_cent);
+    println!("Searching for the comma basis:");
+    for comma in &report.commas {
+        print!("   {:<26} {:>16} {:9.3} cents =", comma.name.as_deref().unwrap_or("unnamed comma"), comma.ratio, comma.cents);
+        for interval in &comma.intervals {
+            print!(" {}", interval.ratio);
+        }
+        println!();
+    }
+    println!();
 
-    let interval_seq = interval_set.to_interval_sequence();
+}
 
-    print!("List of notes that correspond to the interval sequence:");
-    let note_sequence = interval_seq.to_notes(Note::new(&args.starting_note, args.starting_octave));
-    for (inote, note) in note_sequence.iter().enumerate() {
-        if inote % 20 == 0 {
-            println!();
+fn print_result(report: &Report, heji: bool) {
+    if let Some(front) = &report.pareto_front {
+        println!("Pareto front of sequence length and scaling error:");
+        println!("   {:>9} {:>13} {:>20}   Interval counts", "Intervals", "Error (cents)", "Scaling frequency");
+        for interval_set in front {
+            print!("   {:9} {:13.3} {:20.9}  ", interval_set.num_intervals, interval_set.error_cents.unwrap_or(0.0),
+                   interval_set.ratio_float);
+            print_counts(&interval_set.counts);
         }
-        print!(" {}", note);
     }
+
+    let Some(sequence) = &report.sequence else {
+        return;
+    };
+    println!("Found Sequence:");
+    println!("   Number of intervals:   {}", sequence.set.num_intervals);
+    println!("   Scaling frequency:     {:}", sequence.set.ratio_float);
+    println!("   Scaling monzo:         {}", sequence.set.monzo);
+    println!("   Scaling error (cents): {:}", sequence.set.error_cents.unwrap_or(0.0));
+    println!("   Exact hit:             {}", sequence.exact_hit);
+    println!("   Interval counts:");
+    for count in &sequence.set.counts {
+        println!("      {:<26} {:6}", count.name, count.count);
+    }
+
+    print!("List of notes that correspond to the interval sequence:");
+    print_notes(&sequence.notes, |_| true);
+
     println!();
+    println!("Drift of the notes from 12-TET:");
+    print!("   {:>5} {:>6} {:>20} {:>17} {:>12} {:>12}", "Index", "Note", "Ratio to start", "Deviation (cents)",
+           "Just (Hz)", "12-TET (Hz)");
+    if heji {
+        print!("   HEJI");
+    }
+    println!();
+    for note in &sequence.notes {
+        print!("   {:5} {:>6} {:>20} {:17.3} {:12.3} {:12.3}", note.index, note.note, note.ratio, note.deviation_cents,
+               note.just_freq, note.tempered_freq);
+        if heji {
+            print!("   {}", note.heji);
+        }
+        println!();
+    }
 
-    if args.split_note_sequence {
+    if report.parameters.split_note_sequence {
         println!();
         print!("Instrument 1:");
-        for (inote, note) in note_sequence.iter().enumerate() {
-            if inote % 20 == 0 {
-                println!();
-            }
-            if inote % 2 == 0 {
-                print!(" {}", note);
-            }
-        }
-        println!();
+        print_notes(&sequence.notes, |inote| inote % 2 == 0);
 
         println!();
         print!("Instrument 2:");
-        for (inote, note) in note_sequence.iter().enumerate() {
-            if inote % 20 == 0 {
-                println!();
-            }
-            if (inote+1) % 2 == 0 {
-                print!(" {}", note);
-            }
+        print_notes(&sequence.notes, |inote| (inote+1) % 2 == 0);
+    }
+}
+
+// files of the sequence asked for on the command line, announced in the text output
+fn write_exports(args: &Args, interval_seq: &IntervalSequence, starting_note: Note,
+                 annotated_notes: &[SequenceNote]) -> Result<(), Error> {
+    let announce = |kind: &str, path: &PathBuf| {
+        if args.format == Format::Text {
+            println!();
+            println!("Wrote {} file {}", kind, path.display());
         }
-        println!();
+    };
+
+    if let Some(path) = &args.midi {
+        let options = MidiOptions {bend_range: args.bend_range, tempo: args.tempo, note_duration: args.note_duration,
+                                   mode: args.midi_mode};
+        let tracks = sequence_tracks(annotated_notes, args.split_note_sequence, options.note_duration);
+        write_midi(path, &tracks, &options)?;
+        announce("MIDI", path);
+    }
+
+    if let Some(path) = &args.musicxml {
+        write_musicxml(path, annotated_notes, args.split_note_sequence)?;
+        announce("MusicXML", path);
+    }
+
+    if let Some(path) = &args.lilypond {
+        write_lilypond(path, annotated_notes, &interval_seq.intervals, args.split_note_sequence, args.annotation)?;
+        announce("LilyPond", path);
+    }
+
+    if let Some(path) = &args.abc {
+        write_abc(path, annotated_notes, &interval_seq.intervals, args.split_note_sequence, args.annotation)?;
+        announce("ABC", path);
+    }
+
+    if let Some(path) = &args.scala {
+        let description = format!("Pitches visited from {} to reach {}", starting_note.to_string().trim(), interval_seq.get_freq_scale());
+        write_scala(path, annotated_notes, &description, starting_note, &args.reference)?;
+        announce("Scala", path);
+    }
+
+    if let Some(path) = &args.render {
+        let options = RenderOptions {timbre: args.timbre, sample_rate: args.sample_rate, note_duration: args.note_length,
+                                     attack: args.attack, release: args.release,
+                                     drone: args.drone.then(|| args.reference.tempered_freq(&starting_note))};
+        let tracks = sequence_tracks(annotated_notes, args.split_note_sequence, options.note_duration);
+        write_wav(path, &tracks, &options)?;
+        announce("WAV", path);
     }
+    Ok(())
 }
@@ -0,0 +1,485 @@
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::path::Path;
+use crate::error::Error;
+use crate::interval_sequences::SequenceNote;
+
+// ticks per quarter note
+const TICKS_PER_BEAT: u16 = 480;
+const VELOCITY: u8 = 80;
+// frequency of the MIDI key A4 in the default tuning of synthesizers
+const KEY_A4: i32 = 69;
+const FREQ_A4: f64 = 440.0;
+// channels 2 to 16 play the notes of an MPE zone managed by channel 1
+const MPE_MEMBER_CHANNELS: u8 = 15;
+
+// How the notes are detuned from the keys of the 12-TET
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MidiMode {
+    // pitch bend of the instrument channel before each note
+    PitchBend,
+    // MIDI Tuning Standard single note tuning change of the key before each note
+    Mts,
+    // MIDI Polyphonic Expression, a pitch bend on a channel of its own for each note
+    Mpe
+}
+
+impl FromStr for MidiMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "bend" | "pitch-bend" => Ok(MidiMode::PitchBend),
+            "mts" => Ok(MidiMode::Mts),
+            "mpe" => Ok(MidiMode::Mpe),
+            _ => Err(Error::InvalidOption(format!("'{}' is not a MIDI mode, use bend, mts or mpe", s)))
+        }
+    }
+}
+
+impl fmt::Display for MidiMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MidiMode::PitchBend => write!(f, "bend"),
+            MidiMode::Mts => write!(f, "mts"),
+            MidiMode::Mpe => write!(f, "mpe")
+        }
+    }
+}
+
+// Note of a MIDI track at its exact frequency, timed in beats
+#[derive(Debug, PartialEq, Clone)]
+pub struct MidiNote {
+    pub freq: f64,
+    pub start: f64,
+    pub duration: f64
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MidiOptions {
+    // range of the pitch bend wheel in half steps up and down
+    pub bend_range: u8,
+    // beats per minute
+    pub tempo: f64,
+    // length of a note in beats
+    pub note_duration: f64,
+    pub mode: MidiMode
+}
+
+impl Default for MidiOptions {
+    fn default() -> Self {
+        MidiOptions {bend_range: 2, tempo: 60.0, note_duration: 1.0, mode: MidiMode::PitchBend}
+    }
+}
+
+// Tracks of the notes, one after another. Split sequences alternate between two tracks,
+// each note is held until the same instrument plays its next one, so that every interval
+// of the sequence sounds between both instruments.
+pub fn sequence_tracks(notes: &[SequenceNote], split: bool, note_duration: f64) -> Vec<Vec<MidiNote>> {
+    let ntracks = if split {2} else {1};
+    let mut tracks = vec![Vec::new(); ntracks];
+    for (inote, note) in notes.iter().enumerate() {
+        let last = inote + 1 == notes.len();
+        let slots = if split && !last {2.0} else {1.0};
+        tracks[inote % ntracks].push(MidiNote {
+            freq: note.just_freq,
+            start: inote as f64*note_duration,
+            duration: slots*note_duration
+        });
+    }
+    tracks
+}
+
+// nearest MIDI key of the frequency and the pitch bend value from 0 to 16383 that corrects it
+fn key_and_bend(freq: f64, bend_range: u8) -> Result<(u8, u16), Error> {
+    let pitch = KEY_A4 as f64 + 12.0*(freq/FREQ_A4).log2();
+    let key = pitch.round();
+    if !(0.0..=127.0).contains(&key) {
+        return Err(Error::Export(format!("The note at {:.3} Hz is outside of the MIDI key range", freq)));
+    }
+    let bend = 8192.0 + (pitch - key)/bend_range as f64*8192.0;
+    Ok((key as u8, bend.round().clamp(0.0, 16383.0) as u16))
+}
+
+fn push_variable_length(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        groups.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
+
+// track chunk of the events given as absolute tick and message
+fn track_chunk(mut events: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+    // stable sort, events at the same tick keep their order
+    events.sort_by_key(|(tick, _)| *tick);
+    events.push((events.last().map_or(0, |(tick, _)| *tick), vec![0xff, 0x2f, 0x00]));
+    let mut data = Vec::new();
+    let mut last_tick = 0;
+    for (tick, message) in events {
+        push_variable_length(&mut data, tick - last_tick);
+        data.extend(message);
+        last_tick = tick;
+    }
+    let mut chunk = b"MTrk".to_vec();
+    chunk.extend((data.len() as u32).to_be_bytes());
+    chunk.extend(data);
+    chunk
+}
+
+fn name_event(name: &str) -> Vec<u8> {
+    let mut message = vec![0xff, 0x03];
+    push_variable_length(&mut message, name.len() as u32);
+    message.extend(name.as_bytes());
+    message
+}
+
+// channel configuration messages of a registered parameter, closed by the null parameter
+fn registered_parameter(channel: u8, parameter: u8, value: u8) -> Vec<(u32, Vec<u8>)> {
+    [(101, 0), (100, parameter), (6, value), (38, 0), (101, 127), (100, 127)].iter()
+        .map(|&(controller, value)| (0, vec![0xb0 | channel, controller, value]))
+        .collect()
+}
+
+// real time single note tuning change of the MIDI Tuning Standard, retuning the nearest key to the frequency
+fn tuning_change(freq: f64) -> Result<(u8, Vec<u8>), Error> {
+    let (key, _) = key_and_bend(freq, 1)?;
+    let pitch = KEY_A4 as f64 + 12.0*(freq/FREQ_A4).log2();
+    let mut semitone = pitch.floor();
+    // fraction of the half step in units of 1/16384
+    let mut fraction = ((pitch - semitone)*16384.0).round() as u16;
+    if fraction == 16384 {
+        semitone += 1.0;
+        fraction = 0;
+    }
+    let semitone = semitone.clamp(0.0, 127.0) as u8;
+    let mut message = vec![0xf0];
+    let data = [0x7f, 0x7f, 0x08, 0x02, 0x00, 0x01, key, semitone, (fraction >> 7) as u8, (fraction & 0x7f) as u8, 0xf7];
+    push_variable_length(&mut message, data.len() as u32);
+    message.extend(data);
+    Ok((key, message))
+}
+
+// Channels of the notes of every track. Pitch bends and tuning changes play each instrument on its own
+// channel, MPE plays each note on the member channel that has been silent for the longest time.
+fn assign_channels(tracks: &[Vec<MidiNote>], mode: MidiMode) -> Result<Vec<Vec<u8>>, Error> {
+    if mode != MidiMode::Mpe {
+        if tracks.len() > 16 {
+            return Err(Error::Export(format!("{} instruments exceed the 16 MIDI channels", tracks.len())));
+        }
+        return Ok(tracks.iter().enumerate().map(|(itrack, track)| vec![itrack as u8; track.len()]).collect());
+    }
+    let mut channels: Vec<Vec<u8>> = tracks.iter().map(|track| vec![0; track.len()]).collect();
+    let mut notes: Vec<(usize, usize)> = tracks.iter().enumerate()
+        .flat_map(|(itrack, track)| (0..track.len()).map(move |inote| (itrack, inote)))
+        .collect();
+    notes.sort_by(|a, b| tracks[a.0][a.1].start.total_cmp(&tracks[b.0][b.1].start));
+    // time the member channels become silent
+    let mut silent_from = [f64::NEG_INFINITY; MPE_MEMBER_CHANNELS as usize];
+    for (itrack, inote) in notes {
+        let note = &tracks[itrack][inote];
+        let member = (0..silent_from.len())
+            .filter(|&member| silent_from[member] <= note.start)
+            .min_by(|&a, &b| silent_from[a].total_cmp(&silent_from[b]))
+            .ok_or_else(|| Error::Export(format!("More than {} notes sound at beat {}", MPE_MEMBER_CHANNELS, note.start)))?;
+        silent_from[member] = note.start + note.duration;
+        channels[itrack][inote] = member as u8 + 1;
+    }
+    Ok(channels)
+}
+
+// Standard MIDI file of format 1 with a tempo track and one track per instrument. Every note is played
+// by its nearest key, detuned by a pitch bend or a tuning change right before it starts.
+pub fn to_smf(tracks: &[Vec<MidiNote>], options: &MidiOptions) -> Result<Vec<u8>, Error> {
+    if !(options.tempo > 0.0 && options.note_duration > 0.0) {
+        return Err(Error::InvalidOption(format!("Tempo {} and note duration {} must be positive", options.tempo, options.note_duration)));
+    }
+    let channels = assign_channels(tracks, options.mode)?;
+    let ticks = |beats: f64| (beats*TICKS_PER_BEAT as f64).round() as u32;
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend(6u32.to_be_bytes());
+    bytes.extend(1u16.to_be_bytes());
+    bytes.extend((tracks.len() as u16 + 1).to_be_bytes());
+    bytes.extend(TICKS_PER_BEAT.to_be_bytes());
+
+    let tempo = (60_000_000.0/options.tempo).round() as u32;
+    let mut tempo_message = vec![0xff, 0x51, 0x03];
+    tempo_message.extend(&tempo.to_be_bytes()[1..]);
+    let mut conductor = vec![(0, tempo_message)];
+    if options.mode == MidiMode::Mpe {
+        // lower zone with the first channel as manager and the bend range on every member channel
+        conductor.extend(registered_parameter(0, 6, MPE_MEMBER_CHANNELS));
+        for member in 1..=MPE_MEMBER_CHANNELS {
+            conductor.extend(registered_parameter(member, 0, options.bend_range));
+        }
+    }
+    bytes.extend(track_chunk(conductor));
+
+    for (itrack, (track, channels)) in tracks.iter().zip(&channels).enumerate() {
+        let mut events = vec![(0, name_event(&format!("Instrument {}", itrack+1)))];
+        if options.mode == MidiMode::PitchBend {
+            if let Some(&channel) = channels.first() {
+                events.extend(registered_parameter(channel, 0, options.bend_range));
+            }
+        }
+        for (note, &channel) in track.iter().zip(channels) {
+            let start = ticks(note.start);
+            let key = match options.mode {
+                MidiMode::PitchBend | MidiMode::Mpe => {
+                    let (key, bend) = key_and_bend(note.freq, options.bend_range)?;
+                    events.push((start, vec![0xe0 | channel, (bend & 0x7f) as u8, (bend >> 7) as u8]));
+                    key
+                }
+                MidiMode::Mts => {
+                    let (key, tuning) = tuning_change(note.freq)?;
+                    events.push((start, tuning));
+                    key
+                }
+            };
+            events.push((start, vec![0x90 | channel, key, VELOCITY]));
+            events.push((ticks(note.start + note.duration), vec![0x80 | channel, key, 0]));
+        }
+        // note offs sort before the next note of the same tick
+        events.sort_by_key(|(tick, message)| (*tick, message[0] & 0xf0 != 0x80));
+        bytes.extend(track_chunk(events));
+    }
+    Ok(bytes)
+}
+
+pub fn write_midi(path: &Path, tracks: &[Vec<MidiNote>], options: &MidiOptions) -> Result<(), Error> {
+    fs::write(path, to_smf(tracks, options)?)
+        .map_err(|err| Error::Export(format!("Cannot write MIDI file {}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interval_sequences::test_notes;
+    use crate::just_intervals::JustInterval;
+    use crate::notes::Note;
+
+    fn notes() -> Vec<SequenceNote> {
+        test_notes(&[JustInterval::MajorWholeTone, JustInterval::IMinorWholeTone], Note::new("A", 4))
+    }
+
+    #[test]
+    fn variable_length() {
+        for (value, expected) in [(0, vec![0x00]), (0x7f, vec![0x7f]), (0x80, vec![0x81, 0x00]),
+                                  (0x3fff, vec![0xff, 0x7f]), (0x4000, vec![0x81, 0x80, 0x00])] {
+            let mut bytes = Vec::new();
+            push_variable_length(&mut bytes, value);
+            assert_eq!(bytes, expected);
+        }
+    }
+
+    #[test]
+    fn bend() {
+        assert_eq!(key_and_bend(440.0, 2), Ok((69, 8192)));
+        assert_eq!(key_and_bend(880.0, 2), Ok((81, 8192)));
+        // a syntonic comma above A4 bends up by about a fifth of a half step
+        let (key, bend) = key_and_bend(440.0*81.0/80.0, 2).unwrap();
+        assert_eq!(key, 69);
+        assert_eq!(bend, 8192 + (0.21506/2.0*8192.0f64).round() as u16);
+        // a quarter tone below rounds to the key above with a downward bend
+        let (key, bend) = key_and_bend(440.0*2.0f64.powf(-0.45/12.0), 12).unwrap();
+        assert_eq!((key, bend), (69, 8192 - (0.45/12.0*8192.0f64).round() as u16));
+        assert!(key_and_bend(20000.0, 2).is_err());
+    }
+
+    #[test]
+    fn tracks() {
+        let notes = notes();
+        let tracks = sequence_tracks(&notes, false, 0.5);
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].iter().map(|note| (note.start, note.duration)).collect::<Vec<_>>(),
+                   vec![(0.0, 0.5), (0.5, 0.5), (1.0, 0.5)]);
+        assert!((tracks[0][2].freq - 440.0*81.0/80.0).abs() < 1e-9);
+        // split instruments hold their notes while the other one moves
+        let tracks = sequence_tracks(&notes, true, 1.0);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].iter().map(|note| (note.start, note.duration)).collect::<Vec<_>>(),
+                   vec![(0.0, 2.0), (2.0, 1.0)]);
+        assert_eq!(tracks[1].iter().map(|note| (note.start, note.duration)).collect::<Vec<_>>(),
+                   vec![(1.0, 2.0)]);
+    }
+
+    #[test]
+    fn smf() {
+        let options = MidiOptions {bend_range: 2, tempo: 120.0, note_duration: 1.0, mode: MidiMode::PitchBend};
+        let bytes = to_smf(&sequence_tracks(&notes(), false, 1.0), &options).unwrap();
+        assert_eq!(&bytes[..14], &[b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 1, 0, 2, 0x01, 0xe0]);
+        // tempo track with 500000 microseconds per beat
+        assert_eq!(&bytes[14..22], b"MTrk\x00\x00\x00\x0b");
+        assert_eq!(&bytes[22..29], &[0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20]);
+        assert_eq!(&bytes[29..33], &[0x00, 0xff, 0x2f, 0x00]);
+        // note track with the name, the bend range, three notes and the end of track
+        let track = &bytes[41..];
+        assert_eq!(&bytes[33..37], b"MTrk");
+        assert_eq!(u32::from_be_bytes(bytes[37..41].try_into().unwrap()) as usize, track.len());
+        assert_eq!(&track[..16], b"\x00\xff\x03\x0cInstrument 1");
+        assert_eq!(&track[16..20], &[0x00, 0xb0, 101, 0]);
+        assert_eq!(&track[24..28], &[0x00, 0xb0, 6, 2]);
+        let notes = &track[40..];
+        assert_eq!(&notes[..8], &[0x00, 0xe0, 0x00, 0x40, 0x00, 0x90, 69, VELOCITY]);
+        assert_eq!(&notes[8..12], &[0x83, 0x60, 0x80, 69]);
+        assert_eq!(&notes[13..16], &[0x00, 0xe0, (8192 + 160) as u8 & 0x7f]);
+        assert_eq!(&notes[notes.len()-4..], &[0x00, 0xff, 0x2f, 0x00]);
+    }
+
+    #[test]
+    fn channels() {
+        let bytes = to_smf(&sequence_tracks(&notes(), true, 1.0), &MidiOptions::default()).unwrap();
+        assert_eq!(&bytes[10..12], &[0, 3]);
+        assert!(bytes.windows(3).any(|window| window == [0x91, 71, VELOCITY]));
+        assert!(to_smf(&vec![Vec::new(); 17], &MidiOptions::default()).is_err());
+        assert!(matches!(to_smf(&[], &MidiOptions {tempo: 0.0, ..MidiOptions::default()}), Err(Error::InvalidOption(_))));
+    }
+
+    fn read_variable_length(bytes: &[u8], pos: &mut usize) -> u32 {
+        let mut value = 0;
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+        }
+    }
+
+    // events of every track chunk with their absolute ticks
+    fn parse_smf(bytes: &[u8]) -> Vec<Vec<(u32, Vec<u8>)>> {
+        assert_eq!(&bytes[..4], b"MThd");
+        let mut tracks = Vec::new();
+        let mut pos = 14;
+        while pos < bytes.len() {
+            assert_eq!(&bytes[pos..pos+4], b"MTrk");
+            let end = pos + 8 + u32::from_be_bytes(bytes[pos+4..pos+8].try_into().unwrap()) as usize;
+            pos += 8;
+            let mut events = Vec::new();
+            let mut tick = 0;
+            while pos < end {
+                tick += read_variable_length(bytes, &mut pos);
+                let status = bytes[pos];
+                let length = match status {
+                    0xff => {
+                        let mut data = pos + 2;
+                        let length = read_variable_length(bytes, &mut data) as usize;
+                        data + length - pos
+                    }
+                    0xf0 => {
+                        let mut data = pos + 1;
+                        let length = read_variable_length(bytes, &mut data) as usize;
+                        data + length - pos
+                    }
+                    _ if status & 0xf0 == 0xc0 || status & 0xf0 == 0xd0 => 2,
+                    _ => 3
+                };
+                events.push((tick, bytes[pos..pos+length].to_vec()));
+                pos += length;
+            }
+            assert_eq!(pos, end);
+            assert_eq!(events.last().unwrap().1, vec![0xff, 0x2f, 0x00]);
+            tracks.push(events);
+        }
+        tracks
+    }
+
+    // frequencies of the sounding notes as a synthesizer with pitch bend and tuning tables plays them
+    fn played_freqs(bytes: &[u8]) -> Vec<(u32, f64)> {
+        let mut events: Vec<(u32, usize, Vec<u8>)> = parse_smf(bytes).into_iter().enumerate()
+            .flat_map(|(itrack, track)| track.into_iter().map(move |(tick, message)| (tick, itrack, message)))
+            .collect();
+        events.sort_by_key(|(tick, itrack, _)| (*tick, *itrack));
+        let mut tuning: Vec<f64> = (0..128).map(|key| key as f64).collect();
+        let mut bend = [0.0; 16];
+        let mut bend_range = [2.0; 16];
+        let mut parameter = [(127, 127); 16];
+        let mut freqs = Vec::new();
+        for (tick, _, message) in events {
+            let channel = (message[0] & 0x0f) as usize;
+            match (message[0] & 0xf0, message.get(1), message.get(2)) {
+                (0xb0, Some(&101), Some(&value)) => parameter[channel].0 = value,
+                (0xb0, Some(&100), Some(&value)) => parameter[channel].1 = value,
+                (0xb0, Some(&6), Some(&value)) if parameter[channel] == (0, 0) => bend_range[channel] = value as f64,
+                (0xe0, Some(&lsb), Some(&msb)) =>
+                    bend[channel] = ((msb as i32) << 7 | lsb as i32) as f64/8192.0 - 1.0,
+                (0x90, Some(&key), _) =>
+                    freqs.push((tick, FREQ_A4*2.0f64.powf((tuning[key as usize] + bend[channel]*bend_range[channel] - 69.0)/12.0))),
+                (0xf0, ..) if message[0] == 0xf0 => {
+                    let data = &message[2..];
+                    assert_eq!(&data[..6], &[0x7f, 0x7f, 0x08, 0x02, 0x00, 0x01]);
+                    let fraction = ((data[8] as u16) << 7 | data[9] as u16) as f64/16384.0;
+                    tuning[data[6] as usize] = data[7] as f64 + fraction;
+                }
+                _ => ()
+            }
+        }
+        freqs
+    }
+
+    #[test]
+    fn modes() {
+        assert_eq!("MPE".parse::<MidiMode>(), Ok(MidiMode::Mpe));
+        assert_eq!("bend".parse::<MidiMode>(), Ok(MidiMode::PitchBend));
+        for mode in [MidiMode::PitchBend, MidiMode::Mts, MidiMode::Mpe] {
+            assert_eq!(mode.to_string().parse::<MidiMode>(), Ok(mode));
+        }
+        assert!(matches!("sysex".parse::<MidiMode>(), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn played_back() {
+        let notes = test_notes(&[JustInterval::MajorWholeTone, JustInterval::IMinorWholeTone, JustInterval::IMinorWholeTone,
+                                 JustInterval::MajorWholeTone, JustInterval::MinorThird], Note::new("C", 3));
+        for mode in [MidiMode::PitchBend, MidiMode::Mts, MidiMode::Mpe] {
+            for split in [false, true] {
+                let options = MidiOptions {mode, ..MidiOptions::default()};
+                let tracks = sequence_tracks(&notes, split, options.note_duration);
+                let played = played_freqs(&to_smf(&tracks, &options).unwrap());
+                assert_eq!(played.len(), notes.len());
+                for ((tick, freq), note) in played.iter().zip(&notes) {
+                    // pitch bends are exact to a fortieth of a cent with the range of two half steps
+                    assert!((1200.0*(freq/note.just_freq).log2()).abs() < 0.025, "{} {} {}", mode, split, tick);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mpe_channels() {
+        let tracks = sequence_tracks(&notes(), true, 1.0);
+        let options = MidiOptions {mode: MidiMode::Mpe, ..MidiOptions::default()};
+        let parsed = parse_smf(&to_smf(&tracks, &options).unwrap());
+        // zone of 15 member channels announced on the manager channel
+        assert_eq!(&parsed[0][1..4], &[(0, vec![0xb0, 101, 0]), (0, vec![0xb0, 100, 6]), (0, vec![0xb0, 6, 15])]);
+        let note_on_channels: Vec<u8> = parsed[1..].iter().flatten()
+            .filter(|(_, message)| message[0] & 0xf0 == 0x90)
+            .map(|(_, message)| message[0] & 0x0f)
+            .collect();
+        assert_eq!(note_on_channels, vec![1, 3, 2]);
+        // overlapping notes are spread over the member channels, silent channels are reused
+        let held = vec![vec![MidiNote {freq: 440.0, start: 0.0, duration: 4.0}; 15]];
+        assert!(to_smf(&held, &options).is_ok());
+        let mut held = held;
+        held[0].push(MidiNote {freq: 440.0, start: 1.0, duration: 1.0});
+        assert!(to_smf(&held, &options).is_err());
+        held[0][15].start = 4.0;
+        assert!(to_smf(&held, &options).is_ok());
+    }
+
+    #[test]
+    fn mts_tuning() {
+        let (key, message) = tuning_change(440.0*81.0/80.0).unwrap();
+        assert_eq!(key, 69);
+        let fraction = (0.21506*16384.0f64).round() as u16;
+        assert_eq!(message, vec![0xf0, 11, 0x7f, 0x7f, 0x08, 0x02, 0x00, 0x01, 69, 69,
+                                 (fraction >> 7) as u8, (fraction & 0x7f) as u8, 0xf7]);
+        // just below a key the tuning is given from the key below
+        let (key, message) = tuning_change(440.0*80.0/81.0).unwrap();
+        assert_eq!((key, message[9]), (69, 68));
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+use std::cmp::Ordering;
+use std::ops::{Mul,Div};
+use std::sync::OnceLock;
+use num_traits::{Inv,Pow};
+use rug::{Integer, Rational};
+use crate::error::Error;
+
+// largest prime a monzo can hold an exponent for
+const MAX_PRIME: u32 = 65521;
+
+fn primes() -> &'static [u32] {
+    static PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut is_prime = vec![true; MAX_PRIME as usize + 1];
+        let mut primes = Vec::new();
+        for value in 2..=MAX_PRIME as usize {
+            if is_prime[value] {
+                primes.push(value as u32);
+                for multiple in (value*value..=MAX_PRIME as usize).step_by(value) {
+                    is_prime[multiple] = false;
+                }
+            }
+        }
+        primes
+    })
+}
+
+// prime belonging to the exponent at the given index of a monzo
+pub fn nth_prime(index: usize) -> u32 {
+    primes()[index]
+}
+
+// Frequency ratio stored as the exponents of its prime factors 2, 3, 5, 7, ...
+// e.g. 81/80 = 2^-4 * 3^4 * 5^-1 is the monzo [-4 4 -1>
+#[derive(Debug, PartialEq, Clone, Eq, Hash, Default)]
+pub struct Monzo {
+    // exponents without trailing zeros, so that equal ratios have equal monzos
+    exponents: Vec<i32>
+}
+
+impl Monzo {
+    pub fn new(exponents: Vec<i32>) -> Self {
+        assert!(exponents.len() <= primes().len(), "Monzo exceeds the largest supported prime {}", MAX_PRIME);
+        let mut monzo = Monzo {exponents};
+        monzo.trim();
+        monzo
+    }
+
+    pub fn unison() -> Self {
+        Monzo::default()
+    }
+
+    fn trim(&mut self) {
+        while self.exponents.last() == Some(&0) {
+            self.exponents.pop();
+        }
+    }
+
+    pub fn get_exponents(&self) -> &[i32] {
+        &self.exponents
+    }
+
+    pub fn is_unison(&self) -> bool {
+        self.exponents.is_empty()
+    }
+
+    // largest prime with a non-zero exponent
+    pub fn get_prime_limit(&self) -> u32 {
+        match self.exponents.len() {
+            0 => 1,
+            len => primes()[len-1]
+        }
+    }
+
+    pub fn to_rational(&self) -> Rational {
+        let mut numer = Integer::from(1);
+        let mut denom = Integer::from(1);
+        for (&prime, &exponent) in primes().iter().zip(&self.exponents) {
+            let factor = Integer::from(Integer::u_pow_u(prime, exponent.unsigned_abs()));
+            match exponent.cmp(&0) {
+                Ordering::Greater => numer *= factor,
+                Ordering::Less => denom *= factor,
+                Ordering::Equal => {}
+            }
+        }
+        Rational::from((numer, denom))
+    }
+
+    pub fn cents(&self) -> f64 {
+        primes().iter().zip(&self.exponents)
+            .map(|(&prime, &exponent)| 1200.0 * f64::from(exponent) * f64::from(prime).log2())
+            // folded from +0 as the empty sum of floats is -0
+            .fold(0.0, |acc, cents| acc + cents)
+    }
+
+    // Number of letters of the note names the ratio moves by, e.g. 2 for the thirds 5/4 and 6/5.
+    // Every prime moves by the letters of the nearest size in a 7 letter octave, so that
+    // 3 is an octave and a fifth (11 letters) and 7 two octaves and a minor seventh (20 letters).
+    pub fn get_letter_steps(&self) -> i32 {
+        primes().iter().zip(&self.exponents)
+            .map(|(&prime, &exponent)| exponent * (7.0*f64::from(prime).log2()).round() as i32)
+            .sum()
+    }
+}
+
+impl TryFrom<&Rational> for Monzo {
+    type Error = Error;
+    fn try_from(ratio: &Rational) -> Result<Self, Self::Error> {
+        if *ratio <= 0 {
+            return Err(Error::InvalidRatio(format!("Ratio {} is not positive", ratio)));
+        }
+        let mut exponents = Vec::new();
+        for (value, sign) in [(ratio.numer(), 1), (ratio.denom(), -1)] {
+            let mut value = value.clone();
+            for (iprime, &prime) in primes().iter().enumerate() {
+                if value == 1 {
+                    break;
+                }
+                while value.is_divisible_u(prime) {
+                    value.div_exact_u_mut(prime);
+                    if exponents.len() <= iprime {
+                        exponents.resize(iprime+1, 0);
+                    }
+                    exponents[iprime] += sign;
+                }
+            }
+            if value != 1 {
+                return Err(Error::InvalidRatio(format!("Ratio {} contains prime factors larger than {}", ratio, MAX_PRIME)));
+            }
+        }
+        Ok(Monzo::new(exponents))
+    }
+}
+
+impl From<&Monzo> for Rational {
+    fn from(monzo: &Monzo) -> Self {
+        monzo.to_rational()
+    }
+}
+
+// combines the exponents of two monzos component wise
+fn zip_exponents<F>(lhs: &Monzo, rhs: &Monzo, combine: F) -> Monzo
+    where F: Fn(i32, i32) -> i32 {
+    let len = lhs.exponents.len().max(rhs.exponents.len());
+    let exponent = |monzo: &Monzo, idx: usize| monzo.exponents.get(idx).copied().unwrap_or(0);
+    Monzo::new((0..len).map(|idx| combine(exponent(lhs, idx), exponent(rhs, idx))).collect())
+}
+
+// multiplying frequency ratios adds their exponents
+impl Mul for &Monzo {
+    type Output = Monzo;
+    fn mul(self, rhs: Self) -> Self::Output {
+        zip_exponents(self, rhs, |lhs, rhs| lhs + rhs)
+    }
+}
+
+impl Mul for Monzo {
+    type Output = Monzo;
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl Div for &Monzo {
+    type Output = Monzo;
+    fn div(self, rhs: Self) -> Self::Output {
+        zip_exponents(self, rhs, |lhs, rhs| lhs - rhs)
+    }
+}
+
+// inverting a frequency ratio negates its exponents
+impl Inv for Monzo {
+    type Output = Monzo;
+    fn inv(mut self) -> Self::Output {
+        for exponent in self.exponents.iter_mut() {
+            *exponent = -*exponent;
+        }
+        self
+    }
+}
+
+impl Pow<i32> for &Monzo {
+    type Output = Monzo;
+    fn pow(self, rhs: i32) -> Self::Output {
+        Monzo::new(self.exponents.iter().map(|exponent| exponent*rhs).collect())
+    }
+}
+
+impl PartialOrd for Monzo {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// ordered by the size of the frequency ratio
+impl Ord for Monzo {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            return Ordering::Equal;
+        }
+        // the cents are accurate enough unless the ratios are extremely close
+        let difference = self.cents() - other.cents();
+        if difference.abs() > 1.0e-6 {
+            difference.partial_cmp(&0.0).unwrap()
+        } else {
+            self.to_rational().cmp(&other.to_rational())
+        }
+    }
+}
+
+impl fmt::Display for Monzo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let exponents: Vec<String> = self.exponents.iter().map(|exponent| exponent.to_string()).collect();
+        write!(f, "[{}>", exponents.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monzo(numer: u32, denom: u32) -> Monzo {
+        Monzo::try_from(&Rational::from((numer, denom))).unwrap()
+    }
+
+    #[test]
+    fn from_rational() {
+        assert_eq!(monzo(1,1), Monzo::unison());
+        assert_eq!(monzo(3,2), Monzo::new(vec![-1, 1]));
+        assert_eq!(monzo(81,80), Monzo::new(vec![-4, 4, -1]));
+        assert_eq!(monzo(64,63), Monzo::new(vec![6, -2, 0, -1]));
+        assert_eq!(monzo(13,8), Monzo::new(vec![-3, 0, 0, 0, 0, 1]));
+        assert_eq!(monzo(65521,1).get_prime_limit(), 65521);
+        assert_eq!(Monzo::try_from(&Rational::from((65537,1))).unwrap_err().to_string(),
+                   "Ratio 65537 contains prime factors larger than 65521");
+        assert_eq!(Monzo::try_from(&Rational::from((-3,2))).unwrap_err().to_string(),
+                   "Ratio -3/2 is not positive");
+    }
+
+    #[test]
+    fn to_rational() {
+        for (numer, denom) in [(1,1), (3,2), (81,80), (64,63), (531441,524288), (13,12)] {
+            assert_eq!(monzo(numer, denom).to_rational(), Rational::from((numer, denom)));
+            assert_eq!(Rational::from(&monzo(numer, denom)), Rational::from((numer, denom)));
+        }
+    }
+
+    #[test]
+    fn trailing_zeros() {
+        assert_eq!(Monzo::new(vec![1, 0, 0]), Monzo::new(vec![1]));
+        assert_eq!(Monzo::new(vec![0, 0]), Monzo::unison());
+        assert!(Monzo::new(vec![0]).is_unison());
+    }
+
+    #[test]
+    fn multiply() {
+        assert_eq!(&monzo(9,8) * &monzo(10,9), monzo(5,4));
+        assert_eq!(monzo(5,4) * monzo(4,5), Monzo::unison());
+        assert_eq!(&monzo(9,8) / &monzo(10,9), monzo(81,80));
+        assert_eq!(&monzo(7,4) * &monzo(8,7), monzo(2,1));
+    }
+
+    #[test]
+    fn invert() {
+        assert_eq!(monzo(81,80).inv(), monzo(80,81));
+        assert_eq!(Monzo::unison().inv(), Monzo::unison());
+    }
+
+    #[test]
+    fn power() {
+        assert_eq!((&monzo(3,2)).pow(12), monzo(531441,4096));
+        assert_eq!((&monzo(3,2)).pow(-2), monzo(4,9));
+        assert_eq!((&monzo(3,2)).pow(0), Monzo::unison());
+    }
+
+    #[test]
+    fn cents() {
+        assert!((monzo(2,1).cents() - 1200.0).abs() < 1.0e-9);
+        assert!((monzo(3,2).cents() - 701.955).abs() < 1.0e-3);
+        assert!((monzo(80,81).cents() + 21.506).abs() < 1.0e-3);
+        assert_eq!(Monzo::unison().cents(), 0.0);
+        assert!(Monzo::unison().cents().is_sign_positive());
+    }
+
+    #[test]
+    fn letter_steps() {
+        assert_eq!(Monzo::unison().get_letter_steps(), 0);
+        assert_eq!(monzo(2,1).get_letter_steps(), 7);
+        assert_eq!(monzo(3,2).get_letter_steps(), 4);
+        assert_eq!(monzo(2,3).get_letter_steps(), -4);
+        assert_eq!(monzo(5,4).get_letter_steps(), 2);
+        assert_eq!(monzo(7,4).get_letter_steps(), 6);
+        assert_eq!(monzo(7,5).get_letter_steps(), 4);
+        assert_eq!(monzo(11,8).get_letter_steps(), 3);
+        assert_eq!(monzo(13,12).get_letter_steps(), 1);
+        // commas keep the letter
+        assert_eq!(monzo(81,80).get_letter_steps(), 0);
+        assert_eq!(monzo(64,63).get_letter_steps(), 0);
+        assert_eq!(monzo(128,125).get_letter_steps(), 1);
+    }
+
+    #[test]
+    fn compare() {
+        assert!(monzo(81,80) > Monzo::unison());
+        assert!(monzo(80,81) < Monzo::unison());
+        assert!(monzo(3,2) < monzo(8,5));
+        // schisma sized differences are still ordered exactly
+        let fifths = (&monzo(3,2)).pow(8);
+        let thirds = &(&monzo(5,4)).pow(1) * &(&monzo(2,1)).pow(4);
+        assert_eq!(fifths.cmp(&thirds), fifths.to_rational().cmp(&thirds.to_rational()));
+        assert_eq!(monzo(5,4).cmp(&monzo(10,8)), Ordering::Equal);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(monzo(81,80).to_string(), "[-4 4 -1>");
+        assert_eq!(Monzo::unison().to_string(), "[>");
+    }
+
+    #[test]
+    fn nth_primes() {
+        assert_eq!((0..6).map(nth_prime).collect::<Vec<u32>>(), vec![2, 3, 5, 7, 11, 13]);
+        assert_eq!(nth_prime(monzo(13,8).get_exponents().len()-1), 13);
+    }
+}
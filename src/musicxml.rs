@@ -0,0 +1,206 @@
+use std::fs;
+use std::path::Path;
+use crate::error::Error;
+use crate::interval_sequences::SequenceNote;
+use crate::score::{BEATS_PER_MEASURE, Event, is_treble, measures, part_events};
+
+// octaves of the pitches MusicXML can write, C0 to B9
+const LOWEST_OCTAVE: i32 = 0;
+const HIGHEST_OCTAVE: i32 = 9;
+
+fn note_type(beats: usize) -> (&'static str, bool) {
+    match beats {
+        1 => ("quarter", false),
+        2 => ("half", false),
+        3 => ("half", true),
+        _ => ("whole", false)
+    }
+}
+
+fn accidental_name(accidental: i32) -> Option<&'static str> {
+    match accidental {
+        -2 => Some("flat-flat"),
+        -1 => Some("flat"),
+        1 => Some("sharp"),
+        2 => Some("double-sharp"),
+        _ => None
+    }
+}
+
+// note or rest element of the given beats, tied to the previous and next piece of the same note
+fn push_note(xml: &mut String, note: Option<&SequenceNote>, beats: usize, tied_from: bool, tied_to: bool) -> Result<(), Error> {
+    let (name, dotted) = note_type(beats);
+    xml.push_str("      <note>\n");
+    match note {
+        Some(note) => {
+            if !(LOWEST_OCTAVE..=HIGHEST_OCTAVE).contains(&note.note.get_octave()) {
+                return Err(Error::Export(format!("The note {} is outside of the octaves of MusicXML", note.note.to_string().trim())));
+            }
+            xml.push_str("        <pitch>\n");
+            xml.push_str(&format!("          <step>{}</step>\n", note.note.get_letter().to_str()));
+            if note.note.get_accidental() != 0 {
+                xml.push_str(&format!("          <alter>{}</alter>\n", note.note.get_accidental()));
+            }
+            xml.push_str(&format!("          <octave>{}</octave>\n", note.note.get_octave()));
+            xml.push_str("        </pitch>\n");
+        }
+        None => xml.push_str("        <rest/>\n")
+    }
+    xml.push_str(&format!("        <duration>{}</duration>\n", beats));
+    if tied_from {
+        xml.push_str("        <tie type=\"stop\"/>\n");
+    }
+    if tied_to {
+        xml.push_str("        <tie type=\"start\"/>\n");
+    }
+    xml.push_str(&format!("        <type>{}</type>\n", name));
+    if dotted {
+        xml.push_str("        <dot/>\n");
+    }
+    if let Some(accidental) = note.and_then(|note| accidental_name(note.note.get_accidental())) {
+        if !tied_from {
+            xml.push_str(&format!("        <accidental>{}</accidental>\n", accidental));
+        }
+    }
+    if tied_from || tied_to {
+        xml.push_str("        <notations>\n");
+        if tied_from {
+            xml.push_str("          <tied type=\"stop\"/>\n");
+        }
+        if tied_to {
+            xml.push_str("          <tied type=\"start\"/>\n");
+        }
+        xml.push_str("        </notations>\n");
+    }
+    xml.push_str("      </note>\n");
+    Ok(())
+}
+
+// deviation of the note from 12-TET in cents, written above it
+fn push_deviation(xml: &mut String, note: &SequenceNote) {
+    xml.push_str("      <direction placement=\"above\">\n");
+    xml.push_str("        <direction-type>\n");
+    xml.push_str(&format!("          <words>{:+.1}¢</words>\n", note.deviation_cents));
+    xml.push_str("        </direction-type>\n");
+    xml.push_str("      </direction>\n");
+}
+
+fn push_part(xml: &mut String, ipart: usize, events: &[Event], notes: &[SequenceNote]) -> Result<(), Error> {
+    let (sign, line) = if is_treble(events, notes) {("G", 2)} else {("F", 4)};
+    xml.push_str(&format!("  <part id=\"P{}\">\n", ipart+1));
+    for (imeasure, pieces) in measures(events).iter().enumerate() {
+        xml.push_str(&format!("    <measure number=\"{}\">\n", imeasure+1));
+        if imeasure == 0 {
+            xml.push_str("      <attributes>\n");
+            xml.push_str("        <divisions>1</divisions>\n");
+            xml.push_str("        <key><fifths>0</fifths></key>\n");
+            xml.push_str(&format!("        <time><beats>{}</beats><beat-type>4</beat-type></time>\n", BEATS_PER_MEASURE));
+            xml.push_str(&format!("        <clef><sign>{}</sign><line>{}</line></clef>\n", sign, line));
+            xml.push_str("      </attributes>\n");
+        }
+        for piece in pieces {
+            let note = piece.note.map(|inote| &notes[inote]);
+            if let (Some(note), false) = (note, piece.tied_from) {
+                push_deviation(xml, note);
+            }
+            push_note(xml, note, piece.beats, piece.tied_from, piece.tied_to)?;
+        }
+        xml.push_str("    </measure>\n");
+    }
+    xml.push_str("  </part>\n");
+    Ok(())
+}
+
+// Partwise MusicXML score of the notes in quarter notes, one part per instrument
+pub fn to_musicxml(notes: &[SequenceNote], split: bool) -> Result<String, Error> {
+    let parts = part_events(notes.len(), split);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    xml.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+    xml.push_str("<score-partwise version=\"4.0\">\n");
+    xml.push_str("  <part-list>\n");
+    for ipart in 0..parts.len() {
+        xml.push_str(&format!("    <score-part id=\"P{}\">\n", ipart+1));
+        xml.push_str(&format!("      <part-name>Instrument {}</part-name>\n", ipart+1));
+        xml.push_str("    </score-part>\n");
+    }
+    xml.push_str("  </part-list>\n");
+    for (ipart, events) in parts.iter().enumerate() {
+        push_part(&mut xml, ipart, events, notes)?;
+    }
+    xml.push_str("</score-partwise>\n");
+    Ok(xml)
+}
+
+pub fn write_musicxml(path: &Path, notes: &[SequenceNote], split: bool) -> Result<(), Error> {
+    fs::write(path, to_musicxml(notes, split)?)
+        .map_err(|err| Error::Export(format!("Cannot write MusicXML file {}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interval_sequences::test_notes;
+    use crate::just_intervals::JustInterval;
+    use crate::notes::Note;
+
+    fn notes() -> Vec<SequenceNote> {
+        test_notes(&[JustInterval::MinorThird, JustInterval::MajorWholeTone, JustInterval::MinorWholeTone,
+                     JustInterval::MinorThird], Note::new("C", 4))
+    }
+
+    // contents of every element of the name
+    fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+        let open = format!("<{}>", name);
+        let close = format!("</{}>", name);
+        xml.split(&open).skip(1).map(|rest| &rest[..rest.find(&close).unwrap()]).collect()
+    }
+
+    #[test]
+    fn pitches() {
+        let xml = to_musicxml(&notes(), false).unwrap();
+        assert!(xml.starts_with("<?xml"));
+        assert_eq!(elements(&xml, "part-name"), vec!["Instrument 1"]);
+        let steps = elements(&xml, "step");
+        assert_eq!(steps, vec!["C", "E", "F", "G", "B"]);
+        assert_eq!(elements(&xml, "alter"), vec!["-1", "-1"]);
+        assert_eq!(elements(&xml, "accidental"), vec!["flat", "flat"]);
+        assert_eq!(elements(&xml, "octave"), vec!["4"; 5]);
+        assert_eq!(elements(&xml, "words"), vec!["+0.0¢", "+15.6¢", "+19.6¢", "+2.0¢", "+17.6¢"]);
+        assert!(xml.contains("<clef><sign>G</sign><line>2</line></clef>"));
+    }
+
+    #[test]
+    fn full_measures() {
+        let notes = notes();
+        for split in [false, true] {
+            let xml = to_musicxml(&notes, split).unwrap();
+            // every measure of every part is filled with four beats
+            for measure in xml.split("<measure number=").skip(1) {
+                let measure = &measure[..measure.find("</measure>").unwrap()];
+                let beats: usize = elements(measure, "duration").iter().map(|beats| beats.parse::<usize>().unwrap()).sum();
+                assert_eq!(beats, BEATS_PER_MEASURE);
+            }
+        }
+    }
+
+    #[test]
+    fn split_parts() {
+        let xml = to_musicxml(&notes(), true).unwrap();
+        assert_eq!(elements(&xml, "part-name"), vec!["Instrument 1", "Instrument 2"]);
+        let second = &xml[xml.find("<part id=\"P2\">").unwrap()..];
+        // the second part enters after a rest and holds its second note over the barline
+        assert_eq!(elements(second, "step"), vec!["E", "G", "G"]);
+        assert!(second.contains("<rest/>\n        <duration>1</duration>"));
+        assert_eq!(second.matches("<tie type=\"start\"/>").count(), 1);
+        assert_eq!(second.matches("<tied type=\"stop\"/>").count(), 1);
+        // the tied continuation repeats neither the deviation nor the accidental
+        assert_eq!(elements(second, "words"), vec!["+15.6¢", "+2.0¢"]);
+    }
+
+    #[test]
+    fn octave_range() {
+        let notes = |name, octave| test_notes(&[], Note::new(name, octave));
+        assert_eq!(elements(&to_musicxml(&notes("B", 9), false).unwrap(), "octave"), vec!["9"]);
+        assert_eq!(to_musicxml(&notes("C", -1), false), Err(Error::Export(String::from("The note C-1 is outside of the octaves of MusicXML"))));
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use crate::error::{Error, MIN_OCTAVE, MAX_OCTAVE};
+use crate::intervals::Interval;
+use crate::notes::Note;
+
+// orderings the range search may try before giving up
+const MAX_VISITED: usize = 1_000_000;
+
+// Playable notes of an instrument from the lowest to the highest one, both included
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct NoteRange {
+    low: Note,
+    high: Note
+}
+
+impl NoteRange {
+    pub fn try_new(low: Note, high: Note) -> Result<Self, Error> {
+        if low.half_steps_to(&high) < 0 {
+            return Err(Error::InvalidOption(format!("The range {}..{} ends below its lowest note",
+                                                    low.to_string().trim(), high.to_string().trim())));
+        }
+        Ok(NoteRange {low, high})
+    }
+
+    pub fn get_low(&self) -> Note {
+        self.low
+    }
+
+    pub fn get_high(&self) -> Note {
+        self.high
+    }
+
+    pub fn contains(&self, note: &Note) -> bool {
+        self.low.half_steps_to(note) >= 0 && note.half_steps_to(&self.high) >= 0
+    }
+
+    // half steps of the lowest and highest note above the note
+    fn offsets_from(&self, note: &Note) -> (i32, i32) {
+        (note.half_steps_to(&self.low), note.half_steps_to(&self.high))
+    }
+}
+
+impl FromStr for NoteRange {
+    type Err = Error;
+
+    // lowest and highest note separated by .., e.g. C3..G4
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (low, high) = s.split_once("..")
+            .ok_or_else(|| Error::InvalidOption(format!("'{}' is not a note range, use e.g. C3..G4", s)))?;
+        NoteRange::try_new(low.trim().parse()?, high.trim().parse()?)
+    }
+}
+
+impl fmt::Display for NoteRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.low.to_string().trim(), self.high.to_string().trim())
+    }
+}
+
+// Interval played in place of another one: the interval itself or its octave complement,
+// e.g. a fourth up for a fifth down, which moves the note by an octave
+#[derive(Debug)]
+struct Move {
+    itype: usize,
+    interval: Interval,
+    octaves: i32
+}
+
+// Depth first search for an order of the remaining intervals that keeps every note within the
+// half step offsets allowed for the instrument playing it. Octave shifts of substituted intervals
+// have to cancel out by the end. Orders that failed from a position are never tried again.
+struct RangeSearch<'a> {
+    // distinct intervals and their octave complements
+    types: Vec<Interval>,
+    complements: Vec<Option<Interval>>,
+    // allowed offsets from the starting note of the notes of each instrument
+    bounds: &'a [(i32, i32)],
+    failed: HashSet<(Vec<usize>, i32)>,
+    visited: usize,
+    max_visited: usize,
+    // whether the search stopped at max_visited rather than running out of orders
+    gave_up: bool
+}
+
+impl RangeSearch<'_> {
+    fn order(&mut self, counts: &mut Vec<usize>, preferred: &[usize], offset: i32, octaves: i32,
+             inote: usize, order: &mut Vec<Interval>) -> bool {
+        if counts.iter().all(|&count| count == 0) {
+            return octaves == 0;
+        }
+        self.visited += 1;
+        if self.visited > self.max_visited {
+            self.gave_up = true;
+            return false;
+        }
+        if self.failed.contains(&(counts.clone(), offset)) {
+            return false;
+        }
+        // the octave shifts so far can only be undone by the complements of the remaining intervals
+        let (mut down, mut up) = (0, 0);
+        for (itype, &count) in counts.iter().enumerate() {
+            match &self.complements[itype] {
+                Some(complement) if complement.get_half_steps() < self.types[itype].get_half_steps() => down += count as i32,
+                Some(_) => up += count as i32,
+                None => {}
+            }
+        }
+        if octaves > down || -octaves > up {
+            self.failed.insert((counts.clone(), offset));
+            return false;
+        }
+        let (low, high) = self.bounds[(inote + 1) % self.bounds.len()];
+        for candidate in self.moves(counts, preferred) {
+            let next = offset + candidate.interval.get_half_steps();
+            if next < low || next > high {
+                continue;
+            }
+            counts[candidate.itype] -= 1;
+            order.push(candidate.interval);
+            let rest: Vec<usize> = match preferred.iter().position(|&itype| itype == candidate.itype) {
+                Some(ipreferred) => preferred[..ipreferred].iter().chain(&preferred[ipreferred+1..]).copied().collect(),
+                None => preferred.to_vec()
+            };
+            if self.order(counts, &rest, next, octaves + candidate.octaves, inote + 1, order) {
+                return true;
+            }
+            order.pop();
+            counts[candidate.itype] += 1;
+        }
+        self.failed.insert((counts.clone(), offset));
+        false
+    }
+
+    // the remaining intervals in their preferred order, then their complements
+    fn moves(&self, counts: &[usize], preferred: &[usize]) -> Vec<Move> {
+        let mut itypes: Vec<usize> = Vec::new();
+        for &itype in preferred {
+            if counts[itype] > 0 && !itypes.contains(&itype) {
+                itypes.push(itype);
+            }
+        }
+        let plain = itypes.iter()
+            .map(|&itype| Move {itype, interval: self.types[itype].clone(), octaves: 0});
+        let substituted = itypes.iter()
+            .filter_map(|&itype| self.complements[itype].as_ref().map(|complement| {
+                let octaves = (complement.get_half_steps() - self.types[itype].get_half_steps())/12;
+                Move {itype, interval: complement.clone(), octaves}
+            }));
+        plain.chain(substituted).collect()
+    }
+}
+
+// Reorders the intervals, starting from the given order, so that the notes from the starting note on
+// stay within the ranges, the notes alternating between the instruments of the ranges.
+// Intervals may be replaced by their octave complements as long as the replacements up and down cancel,
+// so the sequence ends on the same note with the same drift.
+pub(crate) fn order_in_range(intervals: &[Interval], candidates: &[Interval], startingnote: Note,
+                             ranges: &[NoteRange]) -> Result<Vec<Interval>, Error> {
+    order_in_range_visiting(intervals, candidates, startingnote, ranges, MAX_VISITED)
+}
+
+fn order_in_range_visiting(intervals: &[Interval], candidates: &[Interval], startingnote: Note,
+                           ranges: &[NoteRange], max_visited: usize) -> Result<Vec<Interval>, Error> {
+    assert!(!ranges.is_empty(), "Ordering within ranges needs at least one range");
+    let instrument = |inote: usize| inote % ranges.len();
+    if !ranges[0].contains(&startingnote) {
+        return Err(Error::OutOfRange(format!("The starting note {} lies outside of the range {} of instrument 1",
+                                             startingnote.to_string().trim(), ranges[0])));
+    }
+    let half_steps: i32 = intervals.iter().map(|interval| interval.get_half_steps()).sum();
+    let last = &ranges[instrument(intervals.len())];
+    let final_note = startingnote.shift(half_steps);
+    if !(MIN_OCTAVE..=MAX_OCTAVE).contains(&final_note.get_octave()) {
+        return Err(Error::OctaveOutOfRange(final_note.get_octave()));
+    }
+    if !last.contains(&final_note) {
+        return Err(Error::OutOfRange(format!("The final note {} lies outside of the range {} of instrument {}",
+                                             final_note.to_string().trim(), last, instrument(intervals.len()) + 1)));
+    }
+
+    let mut types: Vec<Interval> = Vec::new();
+    let mut counts: Vec<usize> = Vec::new();
+    let mut preferred = Vec::with_capacity(intervals.len());
+    for interval in intervals {
+        let itype = match types.iter().position(|known| known == interval) {
+            Some(itype) => itype,
+            None => {
+                types.push(interval.clone());
+                counts.push(0);
+                types.len() - 1
+            }
+        };
+        counts[itype] += 1;
+        preferred.push(itype);
+    }
+    // complements of the same size as a candidate are named after it
+    let complements = types.iter()
+        .map(|interval| interval.octave_complement().map(|complement| {
+            candidates.iter()
+                .flat_map(|candidate| [candidate.clone(), -candidate.clone()])
+                .find(|candidate| candidate.get_monzo() == complement.get_monzo()
+                      && candidate.get_half_steps() == complement.get_half_steps())
+                .unwrap_or(complement)
+        }))
+        .collect();
+    let bounds: Vec<(i32, i32)> = ranges.iter().map(|range| range.offsets_from(&startingnote)).collect();
+    let mut search = RangeSearch {types, complements, bounds: &bounds, failed: HashSet::new(), visited: 0,
+                                  max_visited, gave_up: false};
+    let mut order = Vec::with_capacity(intervals.len());
+    if search.order(&mut counts, &preferred, 0, 0, 0, &mut order) {
+        return Ok(order);
+    }
+    let ranges: Vec<String> = ranges.iter().map(NoteRange::to_string).collect();
+    match search.gave_up {
+        true => Err(Error::RangeSearchLimitExceeded(format!("Gave up after trying {} orders of the {} intervals within {}",
+                                                            max_visited, intervals.len(), ranges.join(" and ")))),
+        false => Err(Error::OutOfRange(format!("No order of the {} intervals keeps the notes within {}",
+                                                intervals.len(), ranges.join(" and "))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::just_intervals::JustInterval;
+    use rug::Rational;
+    use crate::monzo::Monzo;
+    use crate::interval_vocabulary::builtin_vocabulary;
+
+    fn range(s: &str) -> NoteRange {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let c3_g4 = range("C3..G4");
+        assert_eq!((c3_g4.get_low(), c3_g4.get_high()), (Note::new("C", 3), Note::new("G", 4)));
+        assert_eq!(c3_g4.to_string(), "C3..G4");
+        assert_eq!(range(" Bb2 .. F#4 ").to_string(), "Bb2..F#4");
+        assert!(c3_g4.contains(&Note::new("C", 3)) && c3_g4.contains(&Note::new("Abb", 4)));
+        assert!(c3_g4.contains(&Note::new("B#", 2)) && !c3_g4.contains(&Note::new("Cb", 3)));
+        assert_eq!("G4..C3".parse::<NoteRange>().unwrap_err().to_string(), "The range G4..C3 ends below its lowest note");
+        assert!(matches!("C3-G4".parse::<NoteRange>(), Err(Error::InvalidOption(_))));
+        assert_eq!("C3..H4".parse::<NoteRange>(), Err(Error::InvalidNoteName(String::from("H"))));
+    }
+
+    fn ordered(intervals: &[JustInterval], startingnote: Note, ranges: &[&str]) -> Result<Vec<Interval>, Error> {
+        let intervals: Vec<Interval> = intervals.iter().copied().map(Interval::from).collect();
+        let ranges: Vec<NoteRange> = ranges.iter().map(|s| range(s)).collect();
+        order_in_range(&intervals, &builtin_vocabulary(5), startingnote, &ranges)
+    }
+
+    fn notes(order: &[Interval], startingnote: Note) -> Vec<Note> {
+        let mut notes = vec![startingnote];
+        for interval in order {
+            notes.push(notes.last().unwrap().shift_by_interval(interval).unwrap());
+        }
+        notes
+    }
+
+    #[test]
+    fn reorder() {
+        use JustInterval::*;
+        let start = Note::new("C", 4);
+        // already within the range
+        let intervals = [MajorThird, IMinorThird, MajorThird, IMinorThird];
+        assert_eq!(ordered(&intervals, start, &["C4..C5"]).unwrap(),
+                   intervals.iter().copied().map(Interval::from).collect::<Vec<_>>());
+        // three thirds up first would leave the range
+        let intervals = [MajorThird, MajorThird, MajorThird, IPerfectFifth, IPerfectFifth];
+        let order = ordered(&intervals, start, &["F3..F4"]).unwrap();
+        assert!(notes(&order, start).iter().all(|note| range("F3..F4").contains(note)));
+        let mut sorted = order.clone();
+        sorted.sort_by_key(Interval::get_half_steps);
+        assert_eq!(sorted, [IPerfectFifth, IPerfectFifth, MajorThird, MajorThird, MajorThird].map(Interval::from));
+    }
+
+    #[test]
+    fn substitute() {
+        use JustInterval::*;
+        let start = Note::new("C", 4);
+        // a sixth up only fits from C4 and a fifth down only from G4 or above, so after the first two
+        // a fifth down becomes a fourth up and a sixth up a minor third down
+        let intervals = [IPerfectFifth, IPerfectFifth, MajorSixth, MajorSixth];
+        let order = ordered(&intervals, start, &["C4..A4"]).unwrap();
+        assert_eq!(order, [MajorSixth, IPerfectFifth, PerfectFourth, IMinorThird].map(Interval::from));
+        assert_eq!(notes(&order, start).last(), Some(&Note::new("E", 4)));
+        // the drift stays the same
+        let monzo = |intervals: Vec<Interval>| intervals.iter().fold(Monzo::unison(), |acc, interval| &acc * &interval.get_monzo());
+        assert_eq!(monzo(order), monzo(intervals.map(Interval::from).to_vec()));
+    }
+
+    #[test]
+    fn implicit_octave() {
+        // complements outside of the vocabulary are named after the interval they replace
+        let seventh = Interval::new("HarmonicSeventh", Rational::from((7,4)), 10);
+        let start = Note::new("C", 4);
+        let order = order_in_range(&[seventh.clone(), -seventh.clone()], std::slice::from_ref(&seventh), start,
+                                   &[range("C4..E4")]).unwrap();
+        assert_eq!(order.iter().map(Interval::get_name).collect::<Vec<_>>(), vec!["IHarmonicSeventh+8ve", "HarmonicSeventh-8ve"]);
+        assert_eq!(notes(&order, start), vec![Note::new("C", 4), Note::new("D", 4), Note::new("C", 4)]);
+    }
+
+    #[test]
+    fn instruments() {
+        let octave = Interval::new("Octave", Rational::from(2), 12);
+        let start = Note::new("C", 4);
+        let ordered = |intervals: &[Interval], ranges: &[&str]| {
+            let ranges: Vec<NoteRange> = ranges.iter().map(|s| range(s)).collect();
+            order_in_range(intervals, std::slice::from_ref(&octave), start, &ranges)
+        };
+        // the second instrument plays every other note
+        let intervals = [octave.clone(), -octave.clone()];
+        assert!(ordered(&intervals, &["C4..C4"]).is_err());
+        assert_eq!(ordered(&intervals, &["C4..C4", "C3..C3"]).unwrap(), vec![-octave.clone(), octave.clone()]);
+        assert_eq!(ordered(&intervals, &["C4..C4", "C5..C5"]).unwrap(), intervals.to_vec());
+    }
+
+    #[test]
+    fn out_of_range() {
+        use JustInterval::*;
+        let start = Note::new("C", 4);
+        assert_eq!(ordered(&[PerfectFifth], start, &["D4..A4"]).unwrap_err().to_string(),
+                   "The starting note C4 lies outside of the range D4..A4 of instrument 1");
+        assert_eq!(ordered(&[PerfectFifth, PerfectFifth], start, &["C4..C5"]).unwrap_err().to_string(),
+                   "The final note D5 lies outside of the range C4..C5 of instrument 1");
+        assert_eq!(ordered(&[PerfectFifth, PerfectFourth, PerfectFifth, PerfectFourth], Note::new("C", 9), &["C9..B9"]),
+                   Err(Error::OctaveOutOfRange(11)));
+        assert_eq!(ordered(&[PerfectFifth, IMajorWholeTone], start, &["C4..F4"]).unwrap_err().to_string(),
+                   "No order of the 2 intervals keeps the notes within C4..F4");
+        assert_eq!(ordered(&[PerfectFifth, IMajorWholeTone], start, &["C4..F4"]).unwrap_err().exit_code(), 3);
+    }
+
+    #[test]
+    fn search_limit() {
+        use JustInterval::*;
+        let intervals: Vec<Interval> = [MajorThird, IMinorThird, MajorThird, IMinorThird].into_iter().map(Interval::from).collect();
+        let ranges = [range("C4..C5")];
+        let limited = |max_visited| order_in_range_visiting(&intervals, &builtin_vocabulary(5), Note::new("C", 4), &ranges, max_visited);
+        assert!(limited(MAX_VISITED).is_ok());
+        // running out of visits is no proof that no order exists
+        let error = limited(2).unwrap_err();
+        assert_eq!(error, Error::RangeSearchLimitExceeded(String::from("Gave up after trying 2 orders of the 4 intervals within C4..C5")));
+        assert_eq!(error.exit_code(), 4);
+    }
+}
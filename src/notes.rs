@@ -1,68 +1,158 @@
 use std::fmt;
-use std::cmp::Ordering;
-use crate::just_intervals::JustInterval;
-use crate::notevalues::NoteValues;
+use std::str::FromStr;
+use crate::error::{Error, MIN_OCTAVE, MAX_OCTAVE};
+use crate::intervals::Interval;
+use crate::notevalues::{Letter, NoteValues};
 
+// largest number of sharps or flats before a note is respelled
+const MAX_ACCIDENTALS: i32 = 2;
+
+// Note spelled by its letter and accidental, the octave is the one of the letter,
+// so that Cb4 sounds like B3 and B#3 like C4
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Note {
-    val: NoteValues,
+    letter: Letter,
+    // number of sharps, negative for flats
+    accidental: i32,
     octave: i32
 }
 
 impl Note {
-    pub fn new(s: &str, octave: i32) -> Self {
-        let val = NoteValues::from_str(s).unwrap();
-        Note {val, octave}
+    // panics on invalid note names, use try_new for names from user input
+    pub(crate) fn new(s: &str, octave: i32) -> Self {
+        Note::try_new(s, octave).unwrap()
     }
 
-    pub fn next(&self) -> Self {
-        let new_octave = match self.val {
-            NoteValues::B => self.octave+1,
-            _ => self.octave
+    // letter followed by the accidentals #, x (double sharp) or b, e.g. C, F#, Bb, Gx or Ebb
+    pub fn try_new(s: &str, octave: i32) -> Result<Self, Error> {
+        let invalid = || Error::InvalidNoteName(String::from(s));
+        let mut chars = s.chars();
+        let letter = chars.next().and_then(Letter::from_char).ok_or_else(invalid)?;
+        let accidentals = chars.as_str();
+        let accidental = match accidentals.chars().next() {
+            Some('b') if accidentals.chars().all(|c| c == 'b') => -(accidentals.len() as i32),
+            _ if accidentals.chars().all(|c| c == '#' || c == 'x') =>
+                accidentals.chars().map(|c| if c == 'x' {2} else {1}).sum(),
+            _ => return Err(invalid())
         };
-        Self {
-            val: self.val.next(),
-            octave: new_octave
+        if !(MIN_OCTAVE..=MAX_OCTAVE).contains(&octave) {
+            return Err(Error::OctaveOutOfRange(octave));
         }
+        Ok(Note {letter, accidental, octave})
+    }
+
+    // note with the given number of half steps above C0, spelled with sharps
+    fn from_pitch(pitch: i32) -> Self {
+        let (letter, accidental) = NoteValues::from_index(pitch).get_spelling();
+        Note {letter, accidental, octave: pitch.div_euclid(12)}
+    }
+
+    pub fn get_letter(&self) -> Letter {
+        self.letter
+    }
+
+    pub fn get_accidental(&self) -> i32 {
+        self.accidental
+    }
+
+    pub fn get_octave(&self) -> i32 {
+        self.octave
+    }
+
+    // sounding pitch class, regardless of the spelling
+    pub fn get_pitch_class(&self) -> NoteValues {
+        NoteValues::from_index(self.pitch())
+    }
+
+    // half steps above C0
+    fn pitch(&self) -> i32 {
+        12*self.octave + self.letter.get_half_steps() + self.accidental
+    }
+
+    // number of the MIDI key, 60 for C4
+    pub fn get_midi_number(&self) -> i32 {
+        self.pitch() + 12
+    }
+
+    // half steps from this note up to the other note
+    pub fn half_steps_to(&self, other: &Note) -> i32 {
+        other.pitch() - self.pitch()
+    }
+
+    // whether both notes sound the same, e.g. C#4 and Db4
+    pub fn is_enharmonic(&self, other: &Note) -> bool {
+        self.half_steps_to(other) == 0
+    }
+
+    pub fn next(&self) -> Self {
+        self.shift(1)
     }
 
     pub fn prev(&self) -> Self {
-        let new_octave = match self.val {
-            NoteValues::C => self.octave-1,
-            _ => self.octave
-        };
-        Self {
-            val: self.val.prev(),
-            octave: new_octave
-        }
+        self.shift(-1)
     }
 
     pub fn lower(&self, steps: u32) -> Self {
-        match steps {
-            0 => *self,
-            _ => self.prev().lower(steps-1)
-        }
+        self.shift(-(steps as i32))
     }
 
     pub fn raise(&self, steps: u32) -> Self {
+        self.shift(steps as i32)
+    }
+
+    // moves by half steps, the new note is spelled with sharps
+    pub fn shift(&self, steps: i32) -> Self {
         match steps {
             0 => *self,
-            _ => self.next().raise(steps-1)
+            _ => Note::from_pitch(self.pitch() + steps)
         }
     }
 
-    pub fn shift(&self, steps: i32) -> Self {
-        match steps.cmp(&0) {
-            Ordering::Less => self.lower(-steps as u32),
-            Ordering::Equal => *self,
-            Ordering::Greater => self.raise(steps as u32)
-        }
+    // moves the letter by the letters of the interval and chooses the accidental
+    // that matches its half steps, so that a major third down from C is Ab.
+    // Drifting sequences pile up accidentals, beyond double sharps and flats
+    // the note is respelled enharmonically. Notes beyond the octaves that can be parsed are rejected.
+    pub fn shift_by_interval(&self, intv: &Interval) -> Result<Self, Error> {
+        let note = self.shift_by_letters(intv);
+        let note = match note.accidental.abs() > MAX_ACCIDENTALS {
+            true => note.respell(),
+            false => note
+        };
+        note.in_octave_range()
+    }
+
+    // like shift_by_interval, but keeps the letter however many accidentals pile up. The HEJI signs
+    // refer to this spelling, respelling moves the note by a Pythagorean comma they cannot show.
+    pub fn shift_by_interval_unrespelled(&self, intv: &Interval) -> Result<Self, Error> {
+        self.shift_by_letters(intv).in_octave_range()
     }
 
-    pub fn shift_by_interval(&self, intv: JustInterval) -> Self {
-        self.shift(intv.get_half_steps())
+    fn shift_by_letters(&self, intv: &Interval) -> Self {
+        let letters = self.letter.get_index() + intv.get_letter_steps();
+        let letter = Letter::from_index(letters);
+        let octave = self.octave + letters.div_euclid(7);
+        let accidental = self.pitch() + intv.get_half_steps() - 12*octave - letter.get_half_steps();
+        Note {letter, accidental, octave}
     }
 
+    fn in_octave_range(self) -> Result<Self, Error> {
+        match (MIN_OCTAVE..=MAX_OCTAVE).contains(&self.octave) {
+            true => Ok(self),
+            false => Err(Error::OctaveOutOfRange(self.octave))
+        }
+    }
+
+    // enharmonic spelling with the fewest accidentals, keeping sharps or flats
+    fn respell(&self) -> Self {
+        let pitch = self.pitch();
+        Letter::ALL.into_iter()
+            .map(|letter| {
+                let octave = (pitch - letter.get_half_steps() + 6).div_euclid(12);
+                Note {letter, accidental: pitch - 12*octave - letter.get_half_steps(), octave}
+            })
+            .min_by_key(|note| (note.accidental.abs(), note.accidental.signum() != self.accidental.signum()))
+            .unwrap()
+    }
 }
 
 #[test]
@@ -142,18 +232,133 @@ fn shift() {
     assert_eq!(Note::new("C", 3).shift(-5), Note::new("G", 2));
 }
 
+#[test]
+fn from_str() {
+    assert_eq!(Note::from_str("A4"), Ok(Note::new("A", 4)));
+    assert_eq!(Note::from_str("c#3"), Ok(Note::new("C#", 3)));
+    assert_eq!(Note::from_str("Bb-1"), Ok(Note::new("Bb", -1)));
+    assert!(Note::from_str("Bb-1").unwrap().is_enharmonic(&Note::new("A#", -1)));
+    assert_eq!(Note::from_str("A"), Err(Error::MissingOctave(String::from("A"))));
+    assert_eq!(Note::from_str("H4"), Err(Error::InvalidNoteName(String::from("H"))));
+    assert_eq!(Note::from_str("A4x"), Err(Error::InvalidOctave(String::from("A4x"))));
+    assert_eq!(Note::from_str("C10"), Err(Error::OctaveOutOfRange(10)));
+}
+
+#[test]
+fn try_new() {
+    let note = Note::try_new("Eb", 9).unwrap();
+    assert_eq!((note.get_letter(), note.get_accidental(), note.get_octave()), (Letter::E, -1, 9));
+    let note = Note::try_new("gbb", 3).unwrap();
+    assert_eq!((note.get_letter(), note.get_accidental()), (Letter::G, -2));
+    let note = Note::try_new("F#x", 3).unwrap();
+    assert_eq!((note.get_letter(), note.get_accidental()), (Letter::F, 3));
+    assert_eq!(Note::try_new("b", 3).unwrap().get_accidental(), 0);
+    assert_eq!(Note::try_new("H", 3), Err(Error::InvalidNoteName(String::from("H"))));
+    assert_eq!(Note::try_new("C#b", 3), Err(Error::InvalidNoteName(String::from("C#b"))));
+    assert_eq!(Note::try_new("", 3), Err(Error::InvalidNoteName(String::new())));
+    assert_eq!(Note::try_new("C", -2), Err(Error::OctaveOutOfRange(-2)));
+}
+
+#[test]
+fn half_steps_to() {
+    assert_eq!(Note::new("C", 3).half_steps_to(&Note::new("C", 3)), 0);
+    assert_eq!(Note::new("C", 3).half_steps_to(&Note::new("G", 3)), 7);
+    assert_eq!(Note::new("A", 4).half_steps_to(&Note::new("C", 3)), -21);
+    for steps in -30..30 {
+        assert_eq!(Note::new("D#", 2).half_steps_to(&Note::new("D#", 2).shift(steps)), steps);
+    }
+}
+
+#[test]
+fn enharmonics() {
+    // the octave belongs to the letter
+    assert_eq!(Note::new("Cb", 4).half_steps_to(&Note::new("B", 3)), 0);
+    assert_eq!(Note::new("B#", 3).half_steps_to(&Note::new("C", 4)), 0);
+    assert!(Note::new("Db", 4).is_enharmonic(&Note::new("C#", 4)));
+    assert_ne!(Note::new("Db", 4), Note::new("C#", 4));
+    assert_eq!(Note::new("Db", 4).get_pitch_class(), NoteValues::CSharp);
+    assert_eq!(Note::new("Cb", 4).next(), Note::new("C", 4));
+}
+
 #[test]
 fn shift_by_interval() {
-    assert_eq!(Note::new("C", 3).shift_by_interval(JustInterval::PerfectFifth), Note::new("G", 3));
+    use crate::just_intervals::JustInterval;
+    let shift = |note: &str, octave, interval: JustInterval| Note::new(note, octave).shift_by_interval(&interval.into()).unwrap();
+    assert_eq!(shift("C", 3, JustInterval::PerfectFifth), Note::new("G", 3));
+    assert_eq!(shift("C", 3, JustInterval::IMajorThird), Note::new("Ab", 2));
+    assert_eq!(shift("C", 3, JustInterval::MinorThird), Note::new("Eb", 3));
+    assert_eq!(shift("B", 3, JustInterval::DiatonicSemitone), Note::new("C", 4));
+    assert_eq!(shift("E", 3, JustInterval::DiatonicSemitone), Note::new("F", 3));
+    assert_eq!(shift("C", 3, JustInterval::HarmonicSeventh), Note::new("Bb", 3));
+    assert_eq!(shift("C", 3, JustInterval::SeptimalTritone), Note::new("Gb", 3));
+    assert_eq!(shift("C", 3, JustInterval::UndecimalSuperFourth), Note::new("F#", 3));
+    // accidentals pile up to double sharps and flats
+    assert_eq!(shift("A#", 3, JustInterval::MajorThird), Note::new("Cx", 4));
+    assert_eq!(shift("Db", 3, JustInterval::IMajorThird), Note::new("Bbb", 2));
+    // more than two accidentals are respelled
+    assert_eq!(shift("Bbb", 3, JustInterval::IDiatonicSemitone), Note::new("Ab", 3));
+    assert_eq!(shift("Ex", 3, JustInterval::TridecimalSemitone), Note::new("Fx", 3));
+    assert_eq!(shift("Ex", 3, JustInterval::MajorWholeTone), Note::new("G#", 3));
+    assert_eq!(shift("Fx", 3, JustInterval::MajorWholeTone), Note::new("Gx", 3));
+    let mut note = Note::new("C", 3);
+    for _ in 0..12 {
+        note = note.shift_by_interval(&JustInterval::DiatonicSemitone.into()).unwrap();
+        assert!(note.get_accidental().abs() <= MAX_ACCIDENTALS);
+    }
+    assert!(note.is_enharmonic(&Note::new("C", 4)));
+    // without respelling the letter follows the intervals
+    let mut note = Note::new("C", 3);
+    for _ in 0..12 {
+        note = note.shift_by_interval_unrespelled(&JustInterval::DiatonicSemitone.into()).unwrap();
+    }
+    assert_eq!((note.get_letter(), note.get_accidental(), note.get_octave()), (Letter::A, -9, 4));
+    assert!(note.is_enharmonic(&Note::new("C", 4)));
+    // commas keep the spelling
+    let note = Note::new("D", 3)
+        .shift_by_interval(&JustInterval::MajorWholeTone.into()).unwrap()
+        .shift_by_interval(&JustInterval::IMinorWholeTone.into()).unwrap();
+    assert_eq!(note, Note::new("D", 3));
+    // the octaves stay within the ones of the note names
+    assert_eq!(Note::new("B", 9).shift_by_interval(&JustInterval::DiatonicSemitone.into()), Err(Error::OctaveOutOfRange(10)));
+    assert_eq!(Note::new("C", -1).shift_by_interval(&JustInterval::IDiatonicSemitone.into()), Err(Error::OctaveOutOfRange(-2)));
+}
+
+#[test]
+fn display() {
+    assert_eq!(Note::new("C", 3).to_string(), " C3");
+    assert_eq!(Note::new("Ab", 2).to_string(), "Ab2");
+    assert_eq!(Note::new("Gx", 4).to_string(), "Gx4");
+    assert_eq!(Note::new("F###", 4).to_string(), "F#x4");
+    assert_eq!(Note::new("Ebb", -1).to_string(), "Ebb-1");
+}
+
+// note name followed by the octave, e.g. A4, C#3 or Bb-1
+impl FromStr for Note {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.find(|c: char| c.is_ascii_digit() || c == '-')
+            .ok_or_else(|| Error::MissingOctave(String::from(s)))?;
+        let octave = s[split..].parse().map_err(|_| Error::InvalidOctave(String::from(s)))?;
+        Note::try_new(&s[..split], octave)
+    }
+}
+
+// sharps are written as # and pairs of sharps as x, e.g. #x for a triple sharp
+fn accidental_str(accidental: i32) -> String {
+    match accidental {
+        0.. => "#".repeat(accidental as usize % 2) + &"x".repeat(accidental as usize / 2),
+        _ => "b".repeat(accidental.unsigned_abs() as usize)
+    }
 }
 
 impl fmt::Display for Note {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.val.to_str().len() == 1 {
-            write!(f, " {}{}", self.val.to_str(), self.octave)
+        let name = format!("{}{}", self.letter.to_str(), accidental_str(self.accidental));
+        if name.len() == 1 {
+            write!(f, " {}{}", name, self.octave)
         } else {
-            write!(f, "{}{}", self.val.to_str(), self.octave)
+            write!(f, "{}{}", name, self.octave)
         }
-
     }
 }
@@ -1,3 +1,61 @@
+use std::str::FromStr;
+use crate::error::Error;
+
+// letter of a note name
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Letter {
+    C,
+    D,
+    E,
+    F,
+    G,
+    A,
+    B
+}
+
+impl Letter {
+    pub const ALL: [Letter; 7] = [Letter::C, Letter::D, Letter::E, Letter::F, Letter::G, Letter::A, Letter::B];
+
+    // letter at the position within the octave, wrapping around for other octaves
+    pub fn from_index(index: i32) -> Self {
+        Letter::ALL[index.rem_euclid(7) as usize]
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        Letter::ALL.into_iter().find(|letter| letter.to_str().starts_with(c.to_ascii_uppercase()))
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Letter::C => "C",
+            Letter::D => "D",
+            Letter::E => "E",
+            Letter::F => "F",
+            Letter::G => "G",
+            Letter::A => "A",
+            Letter::B => "B",
+        }
+    }
+
+    // position within the octave, counted in letters from C
+    pub fn get_index(self) -> i32 {
+        Letter::ALL.iter().position(|&letter| letter == self).unwrap() as i32
+    }
+
+    // half steps from C up to the natural note of the letter
+    pub fn get_half_steps(self) -> i32 {
+        match self {
+            Letter::C => 0,
+            Letter::D => 2,
+            Letter::E => 4,
+            Letter::F => 5,
+            Letter::G => 7,
+            Letter::A => 9,
+            Letter::B => 11,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum NoteValues {
     C,
@@ -14,8 +72,10 @@ pub enum NoteValues {
     B
 }
 
-impl NoteValues {
-    pub fn from_str(s: &str) -> Result<Self, &'static str> {
+impl FromStr for NoteValues {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "cb" => Ok(NoteValues::B),
             "c"  => Ok(NoteValues::C),
@@ -38,11 +98,13 @@ impl NoteValues {
             "bb" => Ok(NoteValues::ASharp),
             "b"  => Ok(NoteValues::B),
             "b#" => Ok(NoteValues::C),
-            _ => Err("Invalid Note Name")
+            _ => Err(Error::InvalidNoteName(String::from(s)))
         }
     }
+}
 
-    pub fn to_str(&self) -> &str {
+impl NoteValues {
+    pub fn to_str(self) -> &'static str {
         match self {
             NoteValues::C => "C",
             NoteValues::CSharp => "C#",
@@ -59,6 +121,38 @@ impl NoteValues {
         }
     }
 
+    // position within the octave, counted in half steps from C
+    pub fn get_index(self) -> i32 {
+        match self {
+            NoteValues::C => 0,
+            NoteValues::CSharp => 1,
+            NoteValues::D => 2,
+            NoteValues::DSharp => 3,
+            NoteValues::E => 4,
+            NoteValues::F => 5,
+            NoteValues::FSharp => 6,
+            NoteValues::G => 7,
+            NoteValues::GSharp => 8,
+            NoteValues::A => 9,
+            NoteValues::ASharp => 10,
+            NoteValues::B => 11,
+        }
+    }
+
+    pub fn from_index(index: i32) -> Self {
+        let mut value = NoteValues::C;
+        for _ in 0..index.rem_euclid(12) {
+            value = value.next();
+        }
+        value
+    }
+
+    // letter and accidental of the spelling with sharps
+    pub fn get_spelling(self) -> (Letter, i32) {
+        let letter = Letter::from_char(self.to_str().chars().next().unwrap()).unwrap();
+        (letter, self.get_index() - letter.get_half_steps())
+    }
+
     pub fn next(&self) -> Self {
         match self {
             NoteValues::C =>      NoteValues::CSharp,
@@ -160,4 +254,38 @@ mod tests {
         assert_eq!(NoteValues::ASharp.to_str(), String::from("A#"));
         assert_eq!(NoteValues::B.to_str(), String::from("B"));
     }
+
+    #[test]
+    fn letters() {
+        for (index, letter) in Letter::ALL.into_iter().enumerate() {
+            assert_eq!(letter.get_index(), index as i32);
+            assert_eq!(Letter::from_index(index as i32 - 14), letter);
+            assert_eq!(Letter::from_char(letter.to_str().to_ascii_lowercase().chars().next().unwrap()), Some(letter));
+        }
+        assert_eq!(Letter::from_char('H'), None);
+        assert_eq!(Letter::B.get_half_steps(), 11);
+    }
+
+    #[test]
+    fn spelling() {
+        assert_eq!(NoteValues::C.get_spelling(), (Letter::C, 0));
+        assert_eq!(NoteValues::FSharp.get_spelling(), (Letter::F, 1));
+        assert_eq!(NoteValues::ASharp.get_spelling(), (Letter::A, 1));
+        for index in -12..24 {
+            let value = NoteValues::from_index(index);
+            assert_eq!(value.get_index(), index.rem_euclid(12));
+            let (letter, accidental) = value.get_spelling();
+            assert_eq!(letter.get_half_steps() + accidental, value.get_index());
+        }
+    }
+
+    #[test]
+    fn get_index() {
+        let mut value = NoteValues::C;
+        for index in 0..12 {
+            assert_eq!(value.get_index(), index);
+            value = value.next();
+        }
+        assert_eq!(value, NoteValues::C);
+    }
 }
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::str::FromStr;
+use rug::{Integer, Rational};
+use crate::error::Error;
+use crate::notes::Note;
+use crate::target::parse_ratio;
+
+// Concert pitch the absolute frequencies refer to, e.g. A4=440Hz
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference {
+    note: Note,
+    freq: Rational
+}
+
+impl Reference {
+    pub fn new(note: Note, freq: Rational) -> Self {
+        assert!(freq > 0, "The reference frequency needs to be positive");
+        Reference {note, freq}
+    }
+
+    pub fn get_note(&self) -> Note {
+        self.note
+    }
+
+    pub fn get_freq(&self) -> Rational {
+        self.freq.clone()
+    }
+
+    // exact frequency of the note, if it lies whole octaves from the reference note,
+    // as the equal tempered half steps are irrational
+    pub fn exact_freq(&self, note: &Note) -> Option<Rational> {
+        let half_steps = self.note.half_steps_to(note);
+        if half_steps % 12 != 0 {
+            return None;
+        }
+        let octaves = Rational::from(Integer::from(Integer::u_pow_u(2, (half_steps/12).unsigned_abs())));
+        match half_steps >= 0 {
+            true => Some(self.freq.clone()*octaves),
+            false => Some(self.freq.clone()/octaves)
+        }
+    }
+
+    // 12-TET frequency of the note in Hz
+    pub fn tempered_freq(&self, note: &Note) -> f64 {
+        self.freq.to_f64() * 2.0f64.powf(self.note.half_steps_to(note) as f64/12.0)
+    }
+}
+
+impl Default for Reference {
+    fn default() -> Self {
+        Reference::new(Note::new("A", 4), Rational::from(440))
+    }
+}
+
+// note and frequency separated by =, e.g. A4=442 or C4=261.63Hz
+impl FromStr for Reference {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidReference(format!("'{}' is not a valid reference, use a note and its frequency (A4=442Hz)", s));
+        let (note, freq) = s.trim().split_once('=').ok_or_else(invalid)?;
+        let note = Note::from_str(note.trim()).map_err(|err| Error::InvalidReference(format!("Reference '{}': {}", s, err)))?;
+        let freq = freq.trim();
+        let freq = freq.strip_suffix("Hz").or_else(|| freq.strip_suffix("hz")).unwrap_or(freq);
+        let freq = parse_ratio(freq.trim()).ok_or_else(invalid)?;
+        Ok(Reference::new(note, freq))
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}Hz", self.note.to_string().trim(), self.freq)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("A4=442".parse(), Ok(Reference::new(Note::new("A", 4), Rational::from(442))));
+        assert_eq!(" C4 = 261.5Hz ".parse(), Ok(Reference::new(Note::new("C", 4), Rational::from((523,2)))));
+        assert_eq!("A4=440hz".parse(), Ok(Reference::default()));
+        assert!("A4".parse::<Reference>().is_err());
+        assert!("A4=0".parse::<Reference>().is_err());
+        assert!("H4=440".parse::<Reference>().is_err());
+        assert_eq!(Reference::default().to_string(), "A4=440Hz");
+        assert_eq!("C4=523/2".parse::<Reference>().unwrap().to_string(), "C4=523/2Hz");
+    }
+
+    #[test]
+    fn frequencies() {
+        let reference: Reference = "A4=442".parse().unwrap();
+        assert_eq!(reference.exact_freq(&Note::new("A", 2)), Some(Rational::from((221,2))));
+        assert_eq!(reference.exact_freq(&Note::new("A", 5)), Some(Rational::from(884)));
+        assert_eq!(reference.exact_freq(&Note::new("C", 4)), None);
+        assert!((reference.tempered_freq(&Note::new("A", 3)) - 221.0).abs() < 1e-9);
+        assert!((reference.tempered_freq(&Note::new("E", 5)) - 442.0*2.0f64.powf(7.0/12.0)).abs() < 1e-9);
+        assert!((Reference::default().tempered_freq(&Note::new("C", 4)) - 261.626).abs() < 1e-3);
+        // the spelling does not change the pitch
+        assert_eq!(reference.tempered_freq(&Note::new("Gx", 4)), reference.tempered_freq(&Note::new("A", 4)));
+    }
+}
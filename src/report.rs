@@ -0,0 +1,374 @@
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use crate::commas::Comma;
+use crate::error::Error;
+use crate::interval_sequences::SequenceNote;
+use crate::interval_set::IntervalSet;
+use crate::intervals::Interval;
+
+// version of the JSON report, raised whenever a field changes or disappears
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Output format of the command line tool
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+    Csv
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(Error::InvalidOption(format!("'{}' is not an output format, use text, json or csv", s)))
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+            Format::Csv => write!(f, "csv")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Parameters {
+    pub half_steps: i32,
+    pub target: String,
+    pub target_cents: f64,
+    pub max_err_cents: f64,
+    pub starting_note: String,
+    pub reference: String,
+    pub split_note_sequence: bool,
+    // playable range of the notes of each instrument, empty without limits
+    pub ranges: Vec<String>,
+    pub ordering: String,
+    pub max_intervals: i32,
+    // largest number of intervals of the listed half step satisfying sets
+    pub list_hstep_solutions: Option<i32>,
+    // built-in vocabulary of the prime limit or the vocabulary file
+    pub prime_limit: Option<u32>,
+    pub vocabulary_file: Option<String>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct IntervalEntry {
+    pub name: String,
+    pub ratio: String,
+    pub half_steps: i32,
+    pub cents: f64
+}
+
+impl IntervalEntry {
+    pub fn new(interval: &Interval) -> Self {
+        let ratio = interval.get_freq_scale();
+        IntervalEntry {name: interval.get_name(), ratio: ratio.to_string(), half_steps: interval.get_half_steps(),
+                       cents: 1200.0*ratio.to_f64().log2()}
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CountEntry {
+    pub name: String,
+    pub count: i32
+}
+
+// Interval set with its sequence of intervals
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SetEntry {
+    pub num_intervals: usize,
+    pub half_steps: i32,
+    pub ratio: String,
+    pub ratio_float: f64,
+    pub monzo: String,
+    // distance of the scaling from the target, for the sets searched for it
+    pub error_cents: Option<f64>,
+    pub counts: Vec<CountEntry>,
+    pub intervals: Vec<IntervalEntry>
+}
+
+impl SetEntry {
+    pub fn new(interval_set: &IntervalSet, error_cents: Option<f64>) -> Self {
+        let ratio = interval_set.get_freq_scale();
+        let counts = interval_set.get_basis().iter().zip(interval_set.get_counts())
+            .filter(|(_, count)| **count != 0)
+            .map(|(interval, count)| CountEntry {name: interval.get_name(), count: *count})
+            .collect();
+        let intervals = interval_set.to_interval_sequence().intervals.iter().map(IntervalEntry::new).collect();
+        SetEntry {num_intervals: interval_set.num_intervals(), half_steps: interval_set.get_half_steps(),
+                  ratio_float: ratio.to_f64(), ratio: ratio.to_string(), monzo: interval_set.get_monzo().to_string(),
+                  error_cents, counts, intervals}
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CommaEntry {
+    pub name: Option<String>,
+    pub ratio: String,
+    pub cents: f64,
+    pub intervals: Vec<IntervalEntry>
+}
+
+impl CommaEntry {
+    pub fn new(comma: &Comma) -> Self {
+        CommaEntry {name: comma.get_name().map(String::from), ratio: comma.get_freq_scale().to_string(), cents: comma.cents(),
+                    intervals: comma.get_interval_set().to_interval_sequence().intervals.iter().map(IntervalEntry::new).collect()}
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct NoteEntry {
+    pub index: usize,
+    pub note: String,
+    // interval leading to the note, none for the starting note
+    pub interval: Option<String>,
+    pub ratio: String,
+    pub deviation_cents: f64,
+    pub just_freq: f64,
+    pub tempered_freq: f64,
+    pub heji: String
+}
+
+impl NoteEntry {
+    pub fn new(index: usize, note: &SequenceNote, interval: Option<&Interval>) -> Self {
+        NoteEntry {index, note: note.note.to_string().trim().to_string(), interval: interval.map(Interval::get_name),
+                   ratio: note.get_freq_scale().to_string(), deviation_cents: note.deviation_cents,
+                   just_freq: note.just_freq, tempered_freq: note.tempered_freq, heji: note.heji()}
+    }
+}
+
+// Sequence found by the search
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SequenceEntry {
+    pub set: SetEntry,
+    pub exact_hit: bool,
+    pub notes: Vec<NoteEntry>
+}
+
+// Everything the command line tool finds, printed as text or written for scripts as JSON or CSV
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Report {
+    pub schema_version: u32,
+    pub parameters: Parameters,
+    pub vocabulary: Vec<IntervalEntry>,
+    pub half_step_set: SetEntry,
+    // every half step satisfying set up to a number of intervals, if asked for
+    pub half_step_solutions: Option<Vec<SetEntry>>,
+    pub commas: Vec<CommaEntry>,
+    // either the Pareto front of the search or its shortest sequence
+    pub pareto_front: Option<Vec<SetEntry>>,
+    pub sequence: Option<SequenceEntry>
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        String::from(value)
+    }
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map_or(String::new(), T::to_string)
+}
+
+// row of the CSV table of section, index within the section, field and value
+fn push_row(rows: &mut Vec<[String; 4]>, section: &str, index: Option<usize>, field: &str, value: impl ToString) {
+    rows.push([String::from(section), optional(&index), String::from(field), value.to_string()]);
+}
+
+fn push_set_rows(rows: &mut Vec<[String; 4]>, section: &str, index: Option<usize>, set: &SetEntry) {
+    push_row(rows, section, index, "num_intervals", set.num_intervals);
+    push_row(rows, section, index, "half_steps", set.half_steps);
+    push_row(rows, section, index, "ratio", &set.ratio);
+    push_row(rows, section, index, "ratio_float", set.ratio_float);
+    push_row(rows, section, index, "monzo", &set.monzo);
+    push_row(rows, section, index, "error_cents", optional(&set.error_cents));
+    for count in &set.counts {
+        push_row(rows, section, index, &format!("count:{}", count.name), count.count);
+    }
+    push_row(rows, section, index, "intervals", interval_names(&set.intervals));
+}
+
+fn interval_names(intervals: &[IntervalEntry]) -> String {
+    intervals.iter().map(|interval| interval.name.as_str()).collect::<Vec<_>>().join(" ")
+}
+
+impl Report {
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    fn csv_rows(&self) -> Vec<[String; 4]> {
+        let mut rows = Vec::new();
+        push_row(&mut rows, "report", None, "schema_version", self.schema_version);
+        let parameters = &self.parameters;
+        push_row(&mut rows, "parameters", None, "half_steps", parameters.half_steps);
+        push_row(&mut rows, "parameters", None, "target", &parameters.target);
+        push_row(&mut rows, "parameters", None, "target_cents", parameters.target_cents);
+        push_row(&mut rows, "parameters", None, "max_err_cents", parameters.max_err_cents);
+        push_row(&mut rows, "parameters", None, "starting_note", &parameters.starting_note);
+        push_row(&mut rows, "parameters", None, "reference", &parameters.reference);
+        push_row(&mut rows, "parameters", None, "split_note_sequence", parameters.split_note_sequence);
+        push_row(&mut rows, "parameters", None, "ranges", parameters.ranges.join(" "));
+        push_row(&mut rows, "parameters", None, "ordering", &parameters.ordering);
+        push_row(&mut rows, "parameters", None, "max_intervals", parameters.max_intervals);
+        push_row(&mut rows, "parameters", None, "list_hstep_solutions", optional(&parameters.list_hstep_solutions));
+        push_row(&mut rows, "parameters", None, "prime_limit", optional(&parameters.prime_limit));
+        push_row(&mut rows, "parameters", None, "vocabulary_file", optional(&parameters.vocabulary_file));
+        for (index, interval) in self.vocabulary.iter().enumerate() {
+            push_row(&mut rows, "vocabulary", Some(index), "name", &interval.name);
+            push_row(&mut rows, "vocabulary", Some(index), "ratio", &interval.ratio);
+            push_row(&mut rows, "vocabulary", Some(index), "half_steps", interval.half_steps);
+            push_row(&mut rows, "vocabulary", Some(index), "cents", interval.cents);
+        }
+        push_set_rows(&mut rows, "half_step_set", None, &self.half_step_set);
+        for (index, set) in self.half_step_solutions.iter().flatten().enumerate() {
+            push_set_rows(&mut rows, "half_step_solution", Some(index), set);
+        }
+        for (index, comma) in self.commas.iter().enumerate() {
+            push_row(&mut rows, "comma", Some(index), "name", optional(&comma.name));
+            push_row(&mut rows, "comma", Some(index), "ratio", &comma.ratio);
+            push_row(&mut rows, "comma", Some(index), "cents", comma.cents);
+            push_row(&mut rows, "comma", Some(index), "intervals", interval_names(&comma.intervals));
+        }
+        for (index, set) in self.pareto_front.iter().flatten().enumerate() {
+            push_set_rows(&mut rows, "pareto_front", Some(index), set);
+        }
+        if let Some(sequence) = &self.sequence {
+            push_set_rows(&mut rows, "sequence", None, &sequence.set);
+            push_row(&mut rows, "sequence", None, "exact_hit", sequence.exact_hit);
+            for note in &sequence.notes {
+                push_row(&mut rows, "note", Some(note.index), "note", &note.note);
+                push_row(&mut rows, "note", Some(note.index), "interval", optional(&note.interval));
+                push_row(&mut rows, "note", Some(note.index), "ratio", &note.ratio);
+                push_row(&mut rows, "note", Some(note.index), "deviation_cents", note.deviation_cents);
+                push_row(&mut rows, "note", Some(note.index), "just_freq", note.just_freq);
+                push_row(&mut rows, "note", Some(note.index), "tempered_freq", note.tempered_freq);
+                push_row(&mut rows, "note", Some(note.index), "heji", &note.heji);
+            }
+        }
+        rows
+    }
+
+    // long table with a row for every value, to be filtered by section and field
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("section,index,field,value\n");
+        for row in self.csv_rows() {
+            csv.push_str(&row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::drift_search::search_drift;
+    use crate::just_intervals::JustInterval;
+    use crate::notes::Note;
+    use crate::reference::Reference;
+
+    fn report() -> Report {
+        let vocabulary: Vec<Interval> = [JustInterval::MajorWholeTone, JustInterval::MinorWholeTone].into_iter().map(Interval::from).collect();
+        let interval_set = search_drift(&vocabulary, 0, 21.506, 0.01, 4).unwrap();
+        let interval_seq = interval_set.to_interval_sequence();
+        let notes = interval_seq.to_annotated_notes(Note::new("C", 4), &Reference::default()).unwrap();
+        Report {
+            schema_version: SCHEMA_VERSION,
+            parameters: Parameters {half_steps: 0, target: String::from("81/80"), target_cents: 21.506, max_err_cents: 0.01,
+                                    starting_note: String::from("C4"), reference: String::from("A4=440Hz"),
+                                    split_note_sequence: false, ranges: vec![String::from("C4..D4")],
+                                    ordering: String::from("random:7"), max_intervals: 4, list_hstep_solutions: None,
+                                    prime_limit: None, vocabulary_file: Some(String::from("steps, tones.toml"))},
+            vocabulary: vocabulary.iter().map(IntervalEntry::new).collect(),
+            half_step_set: SetEntry::new(&IntervalSet::new_with_hstep(0, &vocabulary).unwrap(), None),
+            half_step_solutions: None,
+            commas: Vec::new(),
+            pareto_front: None,
+            sequence: Some(SequenceEntry {
+                set: SetEntry::new(&interval_set, Some(0.0)),
+                exact_hit: true,
+                notes: notes.iter().enumerate()
+                    .map(|(inote, note)| NoteEntry::new(inote, note, inote.checked_sub(1).map(|iinterval| &interval_seq.intervals[iinterval])))
+                    .collect()
+            })
+        }
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!("JSON".parse::<Format>(), Ok(Format::Json));
+        for format in [Format::Text, Format::Json, Format::Csv] {
+            assert_eq!(format.to_string().parse::<Format>(), Ok(format));
+        }
+        assert!(matches!("yaml".parse::<Format>(), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn entries() {
+        let report = report();
+        assert_eq!(report.vocabulary[0], IntervalEntry {name: String::from("MajorWholeTone"), ratio: String::from("9/8"),
+                                                        half_steps: 2, cents: 1200.0*1.125f64.log2()});
+        let sequence = report.sequence.unwrap();
+        assert_eq!(sequence.set.ratio, "81/80");
+        assert_eq!(sequence.set.num_intervals, 2);
+        assert_eq!(sequence.set.counts.iter().map(|count| (count.name.as_str(), count.count)).collect::<Vec<_>>(),
+                   vec![("MajorWholeTone", 1), ("MinorWholeTone", -1)]);
+        assert_eq!(sequence.notes.iter().map(|note| (note.note.as_str(), note.interval.as_deref())).collect::<Vec<_>>(),
+                   vec![("C4", None), ("D4", Some("MajorWholeTone")), ("C4", Some("IMinorWholeTone"))]);
+        assert!((sequence.notes[2].deviation_cents - 21.506).abs() < 1e-3);
+    }
+
+    #[test]
+    fn csv() {
+        let csv = report().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "section,index,field,value");
+        assert_eq!(lines[1], format!("report,,schema_version,{}", SCHEMA_VERSION));
+        assert!(lines.contains(&"parameters,,vocabulary_file,\"steps, tones.toml\""));
+        assert!(lines.contains(&"parameters,,ranges,C4..D4"));
+        assert!(lines.contains(&"parameters,,ordering,random:7"));
+        assert!(lines.contains(&"vocabulary,1,ratio,10/9"));
+        assert!(lines.contains(&"sequence,,count:MinorWholeTone,-1"));
+        assert!(lines.contains(&"sequence,,intervals,MajorWholeTone IMinorWholeTone"));
+        assert!(lines.contains(&"note,1,note,D4"));
+        assert!(lines.contains(&"note,0,interval,"));
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let json: serde_json::Value = serde_json::from_str(&report().to_json()).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["parameters"]["prime_limit"], serde_json::Value::Null);
+        assert_eq!(json["parameters"]["ranges"][0], "C4..D4");
+        assert_eq!(json["half_step_set"]["num_intervals"], 0);
+        assert_eq!(json["sequence"]["set"]["ratio"], "81/80");
+        assert_eq!(json["sequence"]["set"]["intervals"][1]["name"], "IMinorWholeTone");
+        assert_eq!(json["sequence"]["notes"][2]["heji"], "^");
+        assert_eq!(json["sequence"]["exact_hit"], true);
+    }
+}
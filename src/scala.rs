@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::Path;
+use rug::Rational;
+use crate::error::Error;
+use crate::interval_sequences::SequenceNote;
+use crate::notes::Note;
+use crate::reference::Reference;
+
+// MIDI keys retuned by the keyboard mapping
+const FIRST_KEY: i32 = 0;
+const LAST_KEY: i32 = 127;
+
+// ratio moved by octaves into [1, 2)
+fn octave_reduced(ratio: Rational) -> Rational {
+    let mut ratio = ratio;
+    while ratio >= 2 {
+        ratio /= 2;
+    }
+    while ratio < 1 {
+        ratio *= 2;
+    }
+    ratio
+}
+
+// Distinct ratios of the notes to the starting note within one octave, in ascending order starting with 1/1
+pub fn scale_pitches(notes: &[SequenceNote]) -> Vec<Rational> {
+    let mut pitches: Vec<Rational> = notes.iter()
+        .map(|note| octave_reduced(note.get_freq_scale()))
+        .collect();
+    pitches.sort();
+    pitches.dedup();
+    pitches
+}
+
+// Scala scale of the pitches, which start with the implicit 1/1 and are closed by the octave
+pub fn to_scl(pitches: &[Rational], description: &str) -> String {
+    let degrees: Vec<&Rational> = pitches.iter().filter(|pitch| **pitch != 1).collect();
+    let mut scl = format!("! Written by just_out_of_tune\n!\n{}\n {}\n!\n", description, degrees.len() + 1);
+    for degree in degrees {
+        scl.push_str(&format!(" {}\n", degree));
+    }
+    scl.push_str(" 2/1\n");
+    scl
+}
+
+// Scala keyboard mapping of a scale with the number of notes, which puts its first degree
+// on the key of the starting note tuned to its 12-TET frequency and its further degrees on the keys above
+pub fn to_kbm(size: usize, startingnote: Note, reference: &Reference) -> String {
+    let key = startingnote.get_midi_number();
+    let mut kbm = String::from("! Written by just_out_of_tune\n");
+    kbm.push_str(&format!("! Size of map:\n{}\n", size));
+    kbm.push_str(&format!("! First MIDI note number to retune:\n{}\n", FIRST_KEY));
+    kbm.push_str(&format!("! Last MIDI note number to retune:\n{}\n", LAST_KEY));
+    kbm.push_str(&format!("! Middle note where the first entry of the mapping is mapped to:\n{}\n", key));
+    kbm.push_str(&format!("! Reference note for which frequency is given:\n{}\n", key));
+    kbm.push_str(&format!("! Frequency to tune the above note to:\n{:.6}\n", reference.tempered_freq(&startingnote)));
+    kbm.push_str(&format!("! Scale degree to consider as formal octave:\n{}\n", size));
+    kbm.push_str("! Mapping.\n");
+    for degree in 0..size {
+        kbm.push_str(&format!("{}\n", degree));
+    }
+    kbm
+}
+
+// Writes the scale of the pitches the notes visit to the .scl file and its keyboard mapping
+// to the .kbm file of the same name
+pub fn write_scala(path: &Path, notes: &[SequenceNote], description: &str, startingnote: Note,
+                   reference: &Reference) -> Result<(), Error> {
+    let pitches = scale_pitches(notes);
+    let kbm_path = path.with_extension("kbm");
+    fs::write(path, to_scl(&pitches, description))
+        .map_err(|err| Error::Export(format!("Cannot write Scala file {}: {}", path.display(), err)))?;
+    fs::write(&kbm_path, to_kbm(pitches.len(), startingnote, reference))
+        .map_err(|err| Error::Export(format!("Cannot write Scala keyboard mapping {}: {}", kbm_path.display(), err)))
+}
+
+// Ratios of the degrees of a Scala scale. Degrees in cents are rejected,
+// since the intervals of the searches need exact ratios.
+pub fn parse_scl(content: &str) -> Result<Vec<Rational>, Error> {
+    let invalid = |reason: String| Error::InvalidVocabulary(format!("Invalid Scala file: {}", reason));
+    let mut lines = content.lines().filter(|line| !line.starts_with('!'));
+    // the description may be empty
+    lines.next().ok_or_else(|| invalid(String::from("missing description")))?;
+    let count_line = lines.next().ok_or_else(|| invalid(String::from("missing number of notes")))?;
+    let count: usize = count_line.split_whitespace().next().unwrap_or("").parse()
+        .map_err(|_| invalid(format!("'{}' is not a number of notes", count_line.trim())))?;
+    let mut degrees = Vec::with_capacity(count);
+    for line in lines {
+        let Some(degree) = line.split_whitespace().next() else {
+            continue;
+        };
+        if degree.contains('.') {
+            return Err(invalid(format!("degree {} is given in cents instead of as ratio", degree)));
+        }
+        let ratio = Rational::parse(degree)
+            .map(Rational::from)
+            .map_err(|_| invalid(format!("'{}' is not a valid ratio", degree)))?;
+        degrees.push(ratio);
+    }
+    if degrees.len() != count {
+        return Err(invalid(format!("{} degrees listed instead of {}", degrees.len(), count)));
+    }
+    Ok(degrees)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interval_sequences::test_notes;
+    use crate::just_intervals::JustInterval;
+
+    fn notes() -> Vec<SequenceNote> {
+        test_notes(&[JustInterval::PerfectFifth, JustInterval::PerfectFifth, JustInterval::IMajorThird,
+                     JustInterval::IPerfectFourth], Note::new("C", 3))
+    }
+
+    #[test]
+    fn pitches() {
+        assert_eq!(octave_reduced(Rational::from((9, 4))), Rational::from((9, 8)));
+        assert_eq!(octave_reduced(Rational::from((1, 3))), Rational::from((4, 3)));
+        // 1, 3/2, 9/4, 9/5 and 27/20 reduced to an octave
+        assert_eq!(scale_pitches(&notes()), vec![Rational::from(1), Rational::from((9, 8)), Rational::from((27, 20)),
+                                                 Rational::from((3, 2)), Rational::from((9, 5))]);
+    }
+
+    #[test]
+    fn scl() {
+        assert_eq!(to_scl(&scale_pitches(&notes()), "Fifths and thirds"), "\
+! Written by just_out_of_tune
+!
+Fifths and thirds
+ 5
+!
+ 9/8
+ 27/20
+ 3/2
+ 9/5
+ 2/1
+");
+        assert_eq!(to_scl(&[Rational::from(1)], ""), "! Written by just_out_of_tune\n!\n\n 1\n!\n 2/1\n");
+    }
+
+    #[test]
+    fn kbm() {
+        let kbm = to_kbm(3, Note::new("A", 3), &Reference::default());
+        assert_eq!(kbm, "\
+! Written by just_out_of_tune
+! Size of map:
+3
+! First MIDI note number to retune:
+0
+! Last MIDI note number to retune:
+127
+! Middle note where the first entry of the mapping is mapped to:
+57
+! Reference note for which frequency is given:
+57
+! Frequency to tune the above note to:
+220.000000
+! Scale degree to consider as formal octave:
+3
+! Mapping.
+0
+1
+2
+");
+    }
+
+    #[test]
+    fn parse() {
+        let scl = to_scl(&scale_pitches(&notes()), "");
+        assert_eq!(parse_scl(&scl).unwrap(), vec![Rational::from((9, 8)), Rational::from((27, 20)),
+                                                  Rational::from((3, 2)), Rational::from((9, 5)), Rational::from(2)]);
+        // text after the ratios is ignored and integers are ratios too
+        assert_eq!(parse_scl("! comment\nfifth\n2\n 3/2 fifth\n2\n").unwrap(), vec![Rational::from((3, 2)), Rational::from(2)]);
+        let error = |content: &str| parse_scl(content).unwrap_err().to_string();
+        assert_eq!(error("fifth\n1\n701.955\n"), "Invalid Scala file: degree 701.955 is given in cents instead of as ratio");
+        assert_eq!(error("fifth\n2\n3/2\n"), "Invalid Scala file: 1 degrees listed instead of 2");
+        assert_eq!(error("fifth\nmany\n"), "Invalid Scala file: 'many' is not a number of notes");
+        assert_eq!(error("fifth\n1\nthree/2\n"), "Invalid Scala file: 'three/2' is not a valid ratio");
+        assert_eq!(error("! only comments\n"), "Invalid Scala file: missing description");
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::error::Error;
+use crate::interval_sequences::SequenceNote;
+use crate::intervals::Interval;
+
+// quarter notes per measure of the 4/4 time of the scores
+pub(crate) const BEATS_PER_MEASURE: usize = 4;
+// MIDI number of the lowest note written in the treble clef
+const TREBLE_CLEF_FROM: f64 = 60.0;
+
+// note of the sequence or rest held for a number of beats
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Event {
+    pub note: Option<usize>,
+    pub beats: usize
+}
+
+// part of an event within one measure, tied to the parts of the same note in the measures around it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Piece {
+    pub note: Option<usize>,
+    pub beats: usize,
+    pub tied_from: bool,
+    pub tied_to: bool
+}
+
+// Text written under the notes of a score
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Annotation {
+    // deviation of the note from 12-TET
+    Cents,
+    // name of the interval leading to the note
+    Intervals
+}
+
+impl FromStr for Annotation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "cents" => Ok(Annotation::Cents),
+            "intervals" => Ok(Annotation::Intervals),
+            _ => Err(Error::InvalidOption(format!("'{}' is not an annotation, use cents or intervals", s)))
+        }
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Annotation::Cents => write!(f, "cents"),
+            Annotation::Intervals => write!(f, "intervals"),
+        }
+    }
+}
+
+// Events of the parts, one quarter note after another. Split sequences alternate between two parts,
+// each note is held until the same part plays its next one, like the MIDI tracks of the sequence.
+pub(crate) fn part_events(nnotes: usize, split: bool) -> Vec<Vec<Event>> {
+    let nparts = if split {2} else {1};
+    let mut parts = vec![Vec::new(); nparts];
+    for inote in 0..nnotes {
+        let part: &mut Vec<Event> = &mut parts[inote % nparts];
+        if part.is_empty() && inote > 0 {
+            part.push(Event {note: None, beats: inote});
+        }
+        let last = inote + 1 == nnotes;
+        part.push(Event {note: Some(inote), beats: if split && !last {2} else {1}});
+    }
+    parts
+}
+
+// measures of the events, the last one completed by a rest
+pub(crate) fn measures(events: &[Event]) -> Vec<Vec<Piece>> {
+    let total: usize = events.iter().map(|event| event.beats).sum();
+    let rest = Event {note: None, beats: (BEATS_PER_MEASURE - total % BEATS_PER_MEASURE) % BEATS_PER_MEASURE};
+    let mut measures: Vec<Vec<Piece>> = Vec::new();
+    // beats already filled in the last measure
+    let mut filled = BEATS_PER_MEASURE;
+    for event in events.iter().chain(std::iter::once(&rest)) {
+        let mut remaining = event.beats;
+        while remaining > 0 {
+            if filled == BEATS_PER_MEASURE {
+                measures.push(Vec::new());
+                filled = 0;
+            }
+            let beats = remaining.min(BEATS_PER_MEASURE - filled);
+            let tied_from = event.note.is_some() && remaining < event.beats;
+            remaining -= beats;
+            let tied_to = event.note.is_some() && remaining > 0;
+            measures.last_mut().unwrap().push(Piece {note: event.note, beats, tied_from, tied_to});
+            filled += beats;
+        }
+    }
+    measures
+}
+
+// treble clef for parts lying above middle C on average, bass clef below
+pub(crate) fn is_treble(events: &[Event], notes: &[SequenceNote]) -> bool {
+    let pitches: Vec<f64> = events.iter()
+        .filter_map(|event| event.note.map(|inote| notes[inote].note.get_midi_number() as f64))
+        .collect();
+    pitches.iter().sum::<f64>()/pitches.len().max(1) as f64 >= TREBLE_CLEF_FROM
+}
+
+// text under every note, the starting note has no interval leading to it
+pub(crate) fn annotations(intervals: &[Interval], notes: &[SequenceNote], annotation: Annotation) -> Vec<Option<String>> {
+    match annotation {
+        Annotation::Cents => notes.iter().map(|note| Some(format!("{:+.1}", note.deviation_cents))).collect(),
+        Annotation::Intervals => std::iter::once(None)
+            .chain(intervals.iter().map(|interval| Some(interval.get_name())))
+            .collect()
+    }
+}
+
+// notes and intervals of the sequence written in the tests of the scores
+#[cfg(test)]
+pub(crate) fn test_score(startingnote: crate::notes::Note) -> (Vec<SequenceNote>, Vec<Interval>) {
+    use crate::interval_sequences::test_notes;
+    use crate::just_intervals::JustInterval;
+    let intervals = [JustInterval::MajorWholeTone, JustInterval::IMinorThird, JustInterval::MajorThird,
+                     JustInterval::IMinorWholeTone];
+    (test_notes(&intervals, startingnote), intervals.into_iter().map(Interval::from).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events() {
+        assert_eq!(part_events(3, false), vec![vec![Event {note: Some(0), beats: 1}, Event {note: Some(1), beats: 1},
+                                                    Event {note: Some(2), beats: 1}]]);
+        assert_eq!(part_events(4, true), vec![
+            vec![Event {note: Some(0), beats: 2}, Event {note: Some(2), beats: 2}],
+            vec![Event {note: None, beats: 1}, Event {note: Some(1), beats: 2}, Event {note: Some(3), beats: 1}]
+        ]);
+    }
+
+    #[test]
+    fn measure_pieces() {
+        let piece = |note, beats, tied_from, tied_to| Piece {note, beats, tied_from, tied_to};
+        let events = &part_events(6, true)[1];
+        assert_eq!(measures(events), vec![
+            vec![piece(None, 1, false, false), piece(Some(1), 2, false, false), piece(Some(3), 1, false, true)],
+            vec![piece(Some(3), 1, true, false), piece(Some(5), 1, false, false), piece(None, 2, false, false)]
+        ]);
+        assert_eq!(measures(&[]), Vec::<Vec<Piece>>::new());
+        // rests longer than a measure are split without ties
+        assert_eq!(measures(&[Event {note: None, beats: 5}, Event {note: Some(0), beats: 3}]), vec![
+            vec![piece(None, 4, false, false)],
+            vec![piece(None, 1, false, false), piece(Some(0), 3, false, false)]
+        ]);
+    }
+
+    #[test]
+    fn annotation() {
+        assert_eq!("Intervals".parse::<Annotation>(), Ok(Annotation::Intervals));
+        assert_eq!(Annotation::Cents.to_string().parse::<Annotation>(), Ok(Annotation::Cents));
+        assert!(matches!("ratios".parse::<Annotation>(), Err(Error::InvalidOption(_))));
+    }
+}
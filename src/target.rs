@@ -0,0 +1,213 @@
+use std::fmt;
+use std::str::FromStr;
+use rug::{Integer, Rational};
+use crate::error::Error;
+use crate::notes::Note;
+use crate::reference::Reference;
+
+// Frequency scaling the interval sequence should reach
+#[derive(Debug, PartialEq, Clone)]
+pub enum Target {
+    // exact frequency ratio, e.g. 81/80 or 1.05
+    Ratio(Rational),
+    // size in cents, e.g. +21.5c
+    Cents(f64),
+    // pitch in Hz the sequence should end on, e.g. A4@435Hz
+    Pitch(Note, Rational)
+}
+
+// Parses a positive ratio written as a fraction (81/80), an integer or a decimal number (1.05) exactly.
+pub(crate) fn parse_ratio(s: &str) -> Option<Rational> {
+    let ratio = match s.split_once('.') {
+        Some((integer, fraction)) => {
+            if !fraction.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let numer = Integer::from_str(&format!("{}{}", integer, fraction)).ok()?;
+            let denom = Integer::from(Integer::u_pow_u(10, fraction.len() as u32));
+            Rational::from((numer, denom))
+        }
+        None => Rational::from_str(s).ok()?
+    };
+    (ratio > 0).then_some(ratio)
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || Error::InvalidTarget(format!("'{}' is not a valid target, use a ratio (81/80), cents (+21.5c) or a pitch (A4@435Hz)", s));
+        if let Some((note, freq)) = s.split_once('@') {
+            let note = Note::from_str(note.trim()).map_err(|err| Error::InvalidTarget(format!("Target '{}': {}", s, err)))?;
+            let freq = freq.trim();
+            let freq = freq.strip_suffix("Hz").or_else(|| freq.strip_suffix("hz")).unwrap_or(freq);
+            let freq = parse_ratio(freq.trim()).ok_or_else(invalid)?;
+            Ok(Target::Pitch(note, freq))
+        } else if let Some(cents) = s.strip_suffix('c') {
+            let cents: f64 = cents.trim().parse().map_err(|_| invalid())?;
+            if !cents.is_finite() {
+                return Err(invalid());
+            }
+            Ok(Target::Cents(cents))
+        } else {
+            Ok(Target::Ratio(parse_ratio(s).ok_or_else(invalid)?))
+        }
+    }
+}
+
+impl Target {
+    // half steps implied by the target, only pitches fix them
+    pub fn get_half_steps(&self, starting_note: &Note) -> Option<i32> {
+        match self {
+            Target::Pitch(note, _) => Some(starting_note.half_steps_to(note)),
+            _ => None
+        }
+    }
+
+    // exact frequency ratio of the target, if it has one. A pitch is exact if the starting note
+    // is an octave of the reference note, as the equal tempered half steps are irrational.
+    pub fn get_freq_scale(&self, starting_note: &Note, reference: &Reference) -> Option<Rational> {
+        match self {
+            Target::Ratio(ratio) => Some(ratio.clone()),
+            Target::Cents(_) => None,
+            Target::Pitch(_, freq) => reference.exact_freq(starting_note)
+                .map(|starting_freq| freq/starting_freq)
+        }
+    }
+
+    // size of the target in cents, relative to the starting note
+    pub fn cents(&self, starting_note: &Note, reference: &Reference) -> f64 {
+        match self {
+            Target::Ratio(ratio) => ratio_cents(ratio),
+            Target::Cents(cents) => *cents,
+            Target::Pitch(_, freq) => ratio_cents(&(freq/reference.get_freq()))
+                - 100.0*reference.get_note().half_steps_to(starting_note) as f64
+        }
+    }
+
+    // Distance in cents from the frequency scaling to the target. Exact targets are compared
+    // as ratios, so that only the final conversion to cents rounds.
+    pub fn err_cents(&self, freq_scale: &Rational, starting_note: &Note, reference: &Reference) -> f64 {
+        match self.get_freq_scale(starting_note, reference) {
+            Some(target) => ratio_cents(&(freq_scale/target)).abs(),
+            None => (ratio_cents(freq_scale) - self.cents(starting_note, reference)).abs()
+        }
+    }
+
+    // whether the frequency scaling is exactly the target
+    pub fn is_hit(&self, freq_scale: &Rational, starting_note: &Note, reference: &Reference) -> bool {
+        self.get_freq_scale(starting_note, reference).is_some_and(|target| target == *freq_scale)
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Ratio(ratio) => write!(f, "{}", ratio),
+            Target::Cents(cents) => write!(f, "{:+}c", cents),
+            Target::Pitch(note, freq) => write!(f, "{}@{}Hz", note.to_string().trim(), freq)
+        }
+    }
+}
+
+fn ratio_cents(ratio: &Rational) -> f64 {
+    1200.0*ratio.to_f64().log2()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Target, Error> {
+        s.parse()
+    }
+
+    #[test]
+    fn parse_ratios() {
+        assert_eq!(parse("81/80"), Ok(Target::Ratio(Rational::from((81,80)))));
+        assert_eq!(parse(" 3/2 "), Ok(Target::Ratio(Rational::from((3,2)))));
+        assert_eq!(parse("2"), Ok(Target::Ratio(Rational::from(2))));
+        assert_eq!(parse("1.05"), Ok(Target::Ratio(Rational::from((21,20)))));
+        assert_eq!(parse("1.0125"), Ok(Target::Ratio(Rational::from((81,80)))));
+        assert_eq!(parse(".5"), Ok(Target::Ratio(Rational::from((1,2)))));
+        assert!(parse("0").is_err());
+        assert!(parse("-3/2").is_err());
+        assert!(parse("1/0").is_err());
+        assert!(parse("1.0.5").is_err());
+        assert!(parse("fifth").is_err());
+    }
+
+    #[test]
+    fn parse_cents() {
+        assert_eq!(parse("+21.5c"), Ok(Target::Cents(21.5)));
+        assert_eq!(parse("-3c"), Ok(Target::Cents(-3.0)));
+        assert_eq!(parse("0 c"), Ok(Target::Cents(0.0)));
+        assert!(parse("c").is_err());
+        assert!(parse("infc").is_err());
+    }
+
+    #[test]
+    fn parse_pitches() {
+        assert_eq!(parse("A4@435Hz"), Ok(Target::Pitch(Note::new("A", 4), Rational::from(435))));
+        assert_eq!(parse("c#3@138.5hz"), Ok(Target::Pitch(Note::new("C#", 3), Rational::from((277,2)))));
+        assert_eq!(parse("G2 @ 98"), Ok(Target::Pitch(Note::new("G", 2), Rational::from(98))));
+        assert_eq!(parse("A@435Hz"), Err(Error::InvalidTarget(String::from("Target 'A@435Hz': Missing octave in note name 'A'"))));
+        assert!(parse("A4@-435Hz").is_err());
+        assert!(parse("A4@Hz").is_err());
+    }
+
+    #[test]
+    fn display() {
+        for s in ["81/80", "+21.5c", "-3c", "A4@435Hz", "C#3@277/2Hz"] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let c3 = Note::new("C", 3);
+        let reference = Reference::default();
+        let ratio = parse("81/80").unwrap();
+        assert_eq!(ratio.get_half_steps(&c3), None);
+        assert_eq!(ratio.get_freq_scale(&c3, &reference), Some(Rational::from((81,80))));
+        assert!((ratio.cents(&c3, &reference) - 21.506).abs() < 1e-3);
+        let cents = parse("+21.5c").unwrap();
+        assert_eq!(cents.get_freq_scale(&c3, &reference), None);
+        assert_eq!(cents.cents(&c3, &reference), 21.5);
+        // a pitch fixes the half steps, its ratio is only exact from octaves of the reference
+        let pitch = parse("A4@435Hz").unwrap();
+        assert_eq!(pitch.get_half_steps(&c3), Some(21));
+        assert_eq!(pitch.get_freq_scale(&c3, &reference), None);
+        assert!((pitch.cents(&c3, &reference) - (2100.0 + 1200.0*(435.0f64/440.0).log2())).abs() < 1e-9);
+        assert_eq!(pitch.get_freq_scale(&Note::new("A", 2), &reference), Some(Rational::from((435,110))));
+        assert_eq!(pitch.get_freq_scale(&Note::new("A", 5), &reference), Some(Rational::from((435,880))));
+        assert_eq!(pitch.get_half_steps(&Note::new("A", 5)), Some(-12));
+        // the target pitch is relative to the concert pitch
+        let reference: Reference = "A4=435".parse().unwrap();
+        assert_eq!(pitch.get_freq_scale(&Note::new("A", 4), &reference), Some(Rational::from(1)));
+        assert!((pitch.cents(&c3, &reference) - 2100.0).abs() < 1e-9);
+        let reference: Reference = "C3=130".parse().unwrap();
+        assert_eq!(pitch.get_freq_scale(&c3, &reference), Some(Rational::from((87,26))));
+    }
+
+    #[test]
+    fn errors_and_hits() {
+        let c3 = Note::new("C", 3);
+        let reference = Reference::default();
+        let target = parse("81/80").unwrap();
+        assert!(target.is_hit(&Rational::from((81,80)), &c3, &reference));
+        assert_eq!(target.err_cents(&Rational::from((81,80)), &c3, &reference), 0.0);
+        assert!(!target.is_hit(&Rational::from((32805,32768)), &c3, &reference));
+        // the schisma is resolved although both ratios are close in floating point
+        let target = parse("32805/32768").unwrap();
+        assert!((target.err_cents(&Rational::from((32805*4,32768*4+1)), &c3, &reference) - 0.0132).abs() < 1e-3);
+        // the decimal target is exact, so that 21/20 is a hit
+        let target = parse("1.05").unwrap();
+        assert!(target.is_hit(&Rational::from((21,20)), &c3, &reference));
+        // approximate targets never hit exactly
+        let target = parse("+21.5c").unwrap();
+        assert!(!target.is_hit(&Rational::from((81,80)), &c3, &reference));
+        assert!((target.err_cents(&Rational::from((81,80)), &c3, &reference) - 0.006).abs() < 1e-3);
+    }
+}
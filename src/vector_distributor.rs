@@ -1,3 +1,56 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+use crate::error::Error;
+
+// Order in which the elements of a count vector follow each other
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SequenceOrder {
+    // every element spread as evenly as possible over the sequence
+    Even,
+    // nested Bjorklund rhythms, the rarer elements as onsets between the most frequent one
+    Euclidean,
+    // evenly spread elements shuffled by a pseudo random generator from the seed
+    Random(u64),
+    // the element keeping the sum of the sizes closest to zero next
+    Balanced,
+    // elements of positive size first, then the others
+    Grouped
+}
+
+impl FromStr for SequenceOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidOption(format!("'{}' is not an ordering, use even, euclidean, random[:SEED], balanced or grouped", s));
+        let lower = s.trim().to_lowercase();
+        match lower.split_once(':') {
+            Some(("random", seed)) => Ok(SequenceOrder::Random(seed.trim().parse().map_err(|_| invalid())?)),
+            Some(_) => Err(invalid()),
+            None => match lower.as_str() {
+                "even" => Ok(SequenceOrder::Even),
+                "euclidean" | "bjorklund" => Ok(SequenceOrder::Euclidean),
+                "random" => Ok(SequenceOrder::Random(0)),
+                "balanced" => Ok(SequenceOrder::Balanced),
+                "grouped" => Ok(SequenceOrder::Grouped),
+                _ => Err(invalid())
+            }
+        }
+    }
+}
+
+impl fmt::Display for SequenceOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SequenceOrder::Even => write!(f, "even"),
+            SequenceOrder::Euclidean => write!(f, "euclidean"),
+            SequenceOrder::Random(seed) => write!(f, "random:{}", seed),
+            SequenceOrder::Balanced => write!(f, "balanced"),
+            SequenceOrder::Grouped => write!(f, "grouped"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct PrioItem<K> {
     value: K,
@@ -45,6 +98,99 @@ pub fn distribute<T>(counts: &Vec<(usize, T)>) -> Vec<T>
     list
 }
 
+// Bjorklund's algorithm: the onsets and rests are paired off into groups until at most one
+// group is left over, e.g. x..x..x. for 3 onsets in 8 steps
+fn bjorklund(onsets: usize, steps: usize) -> Vec<bool> {
+    let mut groups = vec![vec![true]; onsets];
+    let mut rest = vec![vec![false]; steps - onsets];
+    while rest.len() > 1 && !groups.is_empty() {
+        let paired = groups.len().min(rest.len());
+        let left_over = if groups.len() > paired {groups.split_off(paired)} else {rest.split_off(paired)};
+        for (group, tail) in groups.iter_mut().zip(&rest) {
+            group.extend(tail);
+        }
+        rest = left_over;
+    }
+    groups.into_iter().chain(rest).flatten().collect()
+}
+
+// The most frequent element fills the rests of a Bjorklund rhythm whose onsets are the others,
+// which are ordered the same way in turn
+fn euclidean<T: Clone>(counts: &[(usize, T)]) -> Vec<T> {
+    let mut counts: Vec<(usize, T)> = counts.iter().filter(|(count, _)| *count > 0).cloned().collect();
+    counts.sort_by_key(|(count, _)| Reverse(*count));
+    if counts.is_empty() {
+        return Vec::new();
+    }
+    let (count, value) = counts.remove(0);
+    let onsets: usize = counts.iter().map(|(count, _)| count).sum();
+    let mut others = euclidean(&counts).into_iter();
+    bjorklund(onsets, count + onsets).into_iter()
+        .map(|onset| if onset {others.next().unwrap()} else {value.clone()})
+        .collect()
+}
+
+// SplitMix64 generator, small and good enough to shuffle sequences reproducibly
+struct SplitMix64 {
+    state: u64
+}
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+// Fisher-Yates shuffle
+fn shuffle<T>(mut list: Vec<T>, seed: u64) -> Vec<T> {
+    let mut rng = SplitMix64 {state: seed};
+    for i in (1..list.len()).rev() {
+        let j = (rng.next() % (i as u64 + 1)) as usize;
+        list.swap(i, j);
+    }
+    list
+}
+
+// Greedy walk that always takes the element bringing the sum of the sizes closest to zero,
+// the more frequent one on ties
+fn balanced<T: Clone>(counts: &[(usize, T)], size: impl Fn(&T) -> i32) -> Vec<T> {
+    let mut remaining: Vec<usize> = counts.iter().map(|(count, _)| *count).collect();
+    let total = remaining.iter().sum();
+    let mut position = 0;
+    let mut list = Vec::with_capacity(total);
+    for _ in 0..total {
+        let next = (0..counts.len())
+            .filter(|&idx| remaining[idx] > 0)
+            .min_by_key(|&idx| ((position + size(&counts[idx].1)).abs(), Reverse(remaining[idx])))
+            .unwrap();
+        remaining[next] -= 1;
+        position += size(&counts[next].1);
+        list.push(counts[next].1.clone());
+    }
+    list
+}
+
+// Sequence of the counted elements in the given order, the sizes of the elements decide
+// the balanced and grouped orders
+pub fn order<T>(counts: &Vec<(usize, T)>, order: SequenceOrder, size: impl Fn(&T) -> i32) -> Vec<T>
+    where T: Clone + std::cmp::Eq + std::hash::Hash + std::fmt::Debug {
+    match order {
+        SequenceOrder::Even => distribute(counts),
+        SequenceOrder::Euclidean => euclidean(counts),
+        SequenceOrder::Random(seed) => shuffle(distribute(counts), seed),
+        SequenceOrder::Balanced => balanced(counts, size),
+        SequenceOrder::Grouped => {
+            let (positive, others): (Vec<_>, Vec<_>) = counts.iter().cloned()
+                .partition(|(_, value)| size(value) > 0);
+            distribute(&positive).into_iter().chain(distribute(&others)).collect()
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -69,4 +215,52 @@ mod test {
         assert_eq!(list, vec![0,1,2,3,4,5,6,7,0,1,10,8,9,2,3,4,0,1,5,2,0,13,14,11,12,
                               6,7,4,3,1,0,2,5,1,0,4,3,10,8,9,2,1,0,7,6,5,4,3,2,1,0]);
     }
+
+    #[test]
+    fn orderings() {
+        for (name, order) in [("even", SequenceOrder::Even), ("Bjorklund", SequenceOrder::Euclidean),
+                              ("random:42", SequenceOrder::Random(42)), ("balanced", SequenceOrder::Balanced),
+                              ("grouped", SequenceOrder::Grouped)] {
+            assert_eq!(name.parse::<SequenceOrder>(), Ok(order));
+            assert_eq!(order.to_string().parse::<SequenceOrder>(), Ok(order));
+        }
+        assert_eq!("random".parse::<SequenceOrder>(), Ok(SequenceOrder::Random(0)));
+        assert!(matches!("random:x".parse::<SequenceOrder>(), Err(Error::InvalidOption(_))));
+        assert!(matches!("sorted".parse::<SequenceOrder>(), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn euclidean_rhythms() {
+        let pattern = |onsets, steps| bjorklund(onsets, steps).iter().map(|&onset| if onset {'x'} else {'.'}).collect::<String>();
+        assert_eq!(pattern(3, 8), "x..x..x.");
+        assert_eq!(pattern(5, 8), "x.xx.xx.");
+        assert_eq!(pattern(4, 12), "x..x..x..x..");
+        assert_eq!(pattern(0, 3), "...");
+        assert_eq!(pattern(2, 2), "xx");
+        assert_eq!(euclidean(&[(5, 'a'), (2, 'b'), (1, 'c')]), vec!['c', 'a', 'a', 'b', 'a', 'a', 'b', 'a']);
+        assert_eq!(euclidean(&[(0, 'a'), (3, 'b')]), vec!['b'; 3]);
+        assert_eq!(euclidean::<char>(&[]), vec![]);
+    }
+
+    #[test]
+    fn shuffled() {
+        let list: Vec<usize> = (0..10).collect();
+        let shuffled = shuffle(list.clone(), 7);
+        assert_eq!(shuffle(list.clone(), 7), shuffled);
+        assert_ne!(shuffle(list.clone(), 8), shuffled);
+        let mut sorted = shuffled.clone();
+        sorted.sort();
+        assert_eq!(sorted, list);
+        // first outputs of the reference implementation for seed 1234567
+        let mut rng = SplitMix64 {state: 1234567};
+        assert_eq!([rng.next(), rng.next()], [6457827717110365317, 3203168211198807973]);
+    }
+
+    #[test]
+    fn walks() {
+        let counts = vec![(3, 4), (2, -7)];
+        assert_eq!(order(&counts, SequenceOrder::Balanced, |&size| size), vec![4, -7, 4, 4, -7]);
+        assert_eq!(order(&counts, SequenceOrder::Grouped, |&size| size), vec![4, 4, 4, -7, -7]);
+        assert_eq!(order(&counts, SequenceOrder::Even, |&size| size), distribute(&counts));
+    }
 }
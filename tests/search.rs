@@ -0,0 +1,90 @@
+use rug::Rational;
+use just_out_of_tune::{Error, Interval, IntervalSet, Target, Reference, Note, builtin_vocabulary, comma_basis, search_drift, pareto_front};
+
+fn note(name: &str) -> Note {
+    name.parse().unwrap()
+}
+
+#[test]
+fn syntonic_comma_drift() {
+    let vocabulary = builtin_vocabulary(5);
+    let target: Target = "81/80".parse().unwrap();
+    let starting_note = note("C3");
+    let interval_set = search_drift(&vocabulary, 0, target.cents(&starting_note, &Reference::default()), 0.01, 10).unwrap();
+    assert_eq!(interval_set.get_half_steps(), 0);
+    assert_eq!(interval_set.num_intervals(), 2);
+    assert!(target.is_hit(&interval_set.get_freq_scale(), &starting_note, &Reference::default()));
+}
+
+#[test]
+fn half_step_solution() {
+    for prime_limit in [3, 5, 7, 11, 13] {
+        let vocabulary = builtin_vocabulary(prime_limit);
+        for half_steps in [-25, -1, 0, 7, 100] {
+            let interval_set = IntervalSet::new_with_hstep(half_steps, &vocabulary).unwrap();
+            assert_eq!(interval_set.get_half_steps(), half_steps);
+            // no listed solution is shorter
+            let shortest = IntervalSet::all_with_hstep(half_steps, &vocabulary, interval_set.num_intervals() as i32).unwrap();
+            assert_eq!(shortest[0].num_intervals(), interval_set.num_intervals());
+        }
+    }
+}
+
+#[test]
+fn comma_basis_spans_no_half_steps() {
+    let vocabulary = builtin_vocabulary(7);
+    let commas = comma_basis(&vocabulary);
+    assert_eq!(commas.len(), 3);
+    for comma in &commas {
+        assert_eq!(comma.get_interval_set().get_half_steps(), 0);
+        assert_eq!(comma.get_interval_set().get_freq_scale(), comma.get_freq_scale());
+        assert!(comma.get_freq_scale() > 1);
+    }
+    assert_eq!(commas[0].get_name(), Some("septimal comma"));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn custom_vocabulary() {
+    use just_out_of_tune::interval_vocabulary::vocabulary_from_toml;
+    let vocabulary = vocabulary_from_toml(r#"
+        [[interval]]
+        name = "Fifth"
+        ratio = "3/2"
+
+        [[interval]]
+        name = "Octave"
+        ratio = "2"
+    "#).unwrap();
+    // twelve fifths down seven octaves leave the Pythagorean comma
+    let interval_set = search_drift(&vocabulary, 0, 23.46, 0.01, 20).unwrap();
+    assert_eq!(interval_set.get_freq_scale(), Rational::from((531441,524288)));
+    assert_eq!(interval_set.get_counts(), &[12, -7]);
+}
+
+#[test]
+fn pareto_front_ends_in_tolerance() {
+    let vocabulary = builtin_vocabulary(5);
+    let front = pareto_front(&vocabulary, 0, 50.0, 1.0, 30).unwrap();
+    assert!(!front.is_empty());
+    for pair in front.windows(2) {
+        assert!(pair[0].0.num_intervals() < pair[1].0.num_intervals());
+        assert!(pair[0].1 > pair[1].1);
+    }
+    let (interval_set, err_cents) = front.last().unwrap();
+    assert!(*err_cents <= 1.0);
+    assert_eq!(search_drift(&vocabulary, 0, 50.0, 1.0, 30).unwrap(), *interval_set);
+}
+
+#[test]
+fn unreachable_targets() {
+    let vocabulary = builtin_vocabulary(5);
+    let err = search_drift(&vocabulary, 0, 50.0, 0.001, 3).unwrap_err();
+    assert!(matches!(err, Error::UnreachableTarget {max_intervals: 3, ..}));
+    assert_eq!(err.exit_code(), 3);
+    // whole tones and major thirds only reach even half steps
+    let whole_tones = [Interval::try_new("WholeTone", Rational::from((9,8)), 2).unwrap(),
+                       Interval::try_new("MajorThird", Rational::from((5,4)), 4).unwrap()];
+    assert_eq!(IntervalSet::new_with_hstep(3, &whole_tones), Err(Error::UnreachableHalfSteps {half_steps: 3, multiple: 2}));
+    assert!(IntervalSet::new_with_hstep(6, &whole_tones).is_ok());
+}
@@ -0,0 +1,71 @@
+use rug::Rational;
+use just_out_of_tune::{Error, IntervalSequence, IntervalSet, Reference, JustInterval, Note, NoteRange, NoteValues, SequenceOrder, Target,
+                       builtin_vocabulary, search_drift};
+
+fn note(name: &str) -> Note {
+    name.parse().unwrap()
+}
+
+#[test]
+fn sequence_to_notes() {
+    let mut sequence = IntervalSequence::new();
+    sequence.add_interval(JustInterval::PerfectFifth);
+    sequence.add_interval(JustInterval::IMajorThird);
+    sequence.add_interval(JustInterval::IPerfectFourth);
+    assert_eq!(sequence.get_half_steps(), -2);
+    assert_eq!(sequence.get_freq_scale(), Rational::from((9,10)));
+    let notes = sequence.to_notes(note("C4")).unwrap();
+    assert_eq!(notes, vec![note("C4"), note("G4"), note("Eb4"), note("Bb3")]);
+}
+
+#[test]
+fn drift_sequence_returns_home() {
+    let vocabulary = builtin_vocabulary(5);
+    let starting_note = note("D3");
+    let interval_set = search_drift(&vocabulary, 12, 1200.0 + 21.506, 0.01, 20).unwrap();
+    let sequence = interval_set.to_interval_sequence();
+    assert_eq!(sequence.intervals.len(), interval_set.num_intervals());
+    let notes = sequence.to_notes(starting_note).unwrap();
+    assert_eq!(notes.len(), interval_set.num_intervals() + 1);
+    assert_eq!(notes.first(), Some(&starting_note));
+    assert_eq!(notes.last(), Some(&note("D4")));
+}
+
+#[test]
+fn note_rendering() {
+    assert_eq!("Eb".parse(), Ok(NoteValues::DSharp));
+    assert_eq!("H".parse::<NoteValues>(), Err(Error::InvalidNoteName(String::from("H"))));
+    let eb4: Note = "Eb4".parse().unwrap();
+    assert_eq!(eb4.to_string(), "Eb4");
+    assert_eq!(eb4.shift(-15), note("C3"));
+    assert_eq!(note("C3").half_steps_to(&eb4), 15);
+    assert_eq!(Note::try_new("C", 10), Err(Error::OctaveOutOfRange(10)));
+}
+
+#[test]
+fn pitch_target() {
+    let target: Target = "A4@435Hz".parse().unwrap();
+    let starting_note = note("A3");
+    assert_eq!(target.get_half_steps(&starting_note), Some(12));
+    assert_eq!(target.get_freq_scale(&starting_note, &Reference::default()), Some(Rational::from((87,44))));
+    assert!("H4@435Hz".parse::<Target>().is_err());
+}
+
+#[test]
+fn octave_substitution_keeps_drift() {
+    let interval_set = IntervalSet::new_from_intervals(&builtin_vocabulary(5), &[(JustInterval::IPerfectFifth, 2),
+                                                                               (JustInterval::MajorSixth, 2)]).unwrap();
+    let starting_note = note("C4");
+    let range: NoteRange = "C4..A4".parse().unwrap();
+    let sequence = interval_set.to_interval_sequence_in_range(starting_note, &[range], SequenceOrder::Even).unwrap();
+    let notes = sequence.to_annotated_notes(starting_note, &Reference::default()).unwrap();
+    assert!(notes.iter().all(|note| range.contains(&note.note)));
+    // a fifth down and a sixth up are played an octave higher and lower
+    assert_ne!(sequence.intervals, interval_set.to_interval_sequence().intervals);
+    assert_eq!(sequence.get_half_steps(), interval_set.get_half_steps());
+    assert_eq!(sequence.get_freq_scale(), interval_set.get_freq_scale());
+    let last = notes.last().unwrap();
+    assert_eq!(last.note, note("E4"));
+    assert_eq!(last.get_freq_scale(), Rational::from((100,81)));
+    assert!((last.deviation_cents - (interval_set.get_monzo().cents() - 400.0)).abs() < 1e-9);
+}